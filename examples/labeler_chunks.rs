@@ -0,0 +1,74 @@
+// Compares how finely labelers split the tiers of (4,4) graphs into chunks
+//
+// usage: labeler_chunks [max_verts] [labeler spec]...
+// every tier is generated once with the standard labeler, then relabeled by
+// each candidate. Fewer, larger chunks mean more work for clean_isos
+
+extern crate graph_lib;
+
+use graph_lib::graph::Graph;
+use graph_lib::invariants;
+use graph_lib::labeler::Pipeline;
+use graph_lib::structures::Tier;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let max_verts = args.first().map_or(9, |n| n.parse().expect("max_verts"));
+    let mut specs: Vec<String> = args.iter().skip(1).cloned().collect();
+    if specs.is_empty() {
+        specs = vec![
+            "degree,polygons,conv".to_string(),
+            "degree,polygons:both,conv:both".to_string(),
+            "wl1".to_string(),
+            "degree,polygons,wl1".to_string(),
+            "wl2".to_string(),
+            "degree,walks".to_string(),
+        ];
+    }
+    let labelers: Vec<Pipeline> = specs
+        .iter()
+        .map(|s| s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e)))
+        .collect();
+
+    let standard = Pipeline::standard();
+    let mut tier = Tier::from_graph(Graph::new(1), &standard);
+    for n in 2..=max_verts {
+        tier = tier.generate_next_size(&standard);
+        let graphs: Vec<&Graph> = tier.graphs().collect();
+        let cospectral = invariants::cospectral_classes(graphs.iter().copied());
+        println!(
+            "n = {}, {} graphs, {} of them in {} groups of cospectral red graphs",
+            n,
+            graphs.len(),
+            cospectral.iter().map(|c| c.len()).sum::<usize>(),
+            cospectral.len()
+        );
+        println!(
+            "  {:<32} {:>8} {:>10} {:>12} {:>10}",
+            "labeler", "chunks", "singletons", "max comp", "time"
+        );
+        for (spec, labeler) in specs.iter().zip(labelers.iter()) {
+            let start = Instant::now();
+            let mut relabeled = Tier::default();
+            for g in graphs.iter() {
+                relabeled.insert_checked((*g).clone(), labeler);
+            }
+            let elapsed = start.elapsed();
+            let singletons = relabeled
+                .map
+                .values()
+                .filter(|c| c.checked.len() == 1)
+                .count();
+            let max_comp = relabeled.map.values().map(|c| c.comp).max().unwrap_or(0);
+            println!(
+                "  {:<32} {:>8} {:>10} {:>12} {:>8}ms",
+                spec,
+                relabeled.count_chunks(),
+                singletons,
+                max_comp,
+                elapsed.as_millis()
+            );
+        }
+    }
+}
@@ -1,9 +1,11 @@
 //A very simple, unsafe, but fast bitvec implementation
 //Definitely not complete, but well suited for purpose
 
+use crate::error::Error;
+
 #[derive(Debug, Default, Clone)]
 pub struct BitVec {
-    pub len: u16,
+    pub len: usize,
     pub vec: Vec<u8>,
 }
 
@@ -14,23 +16,55 @@ impl BitVec {
     
     //least significant bits are first for each u64
     pub fn push(&mut self, val: bool) {
-        if (self.len/8) as usize >= self.vec.len() {
+        if self.len/8 >= self.vec.len() {
             self.vec.push(0);
         }
         if val {
-            let word = (self.len/8) as usize;
+            let word = self.len/8;
             let bit = (7 - self.len%8);
             self.vec[word] |= 1 << bit;
         }
         self.len += 1;
     }
 
+    //same bit order as push, index has to be below len, only the bitvec
+    //backend edits single bits
+    #[cfg(feature = "bv_graph")]
+    pub fn set(&mut self, index: usize, val: bool) {
+        let word = index/8;
+        let bit = 7 - index%8;
+        if val {
+            self.vec[word] |= 1 << bit;
+        } else {
+            self.vec[word] &= !(1 << bit);
+        }
+    }
+
     //extremely unsafe, know what you're doing
     pub fn get(&self, index: usize) -> bool {
         let word = index/8;
         let bit = (7 - index%8);
         self.vec[word] & (1 << bit) != 0
     }
+
+    //the edge bits of a graph on num_verts vertices from their base64, which has
+    //to be exactly as long as they need with the bits after the last one clear
+    pub fn from_base64(s: &str, num_verts: usize) -> Result<Self, Error> {
+        let len = num_verts * num_verts.saturating_sub(1) / 2;
+        let vec = base64::decode(s)?;
+        let expected = len.div_ceil(8);
+        if vec.len() != expected {
+            return Err(Error::WrongLength {
+                len: vec.len(),
+                expected,
+                num_verts,
+            });
+        }
+        if !len.is_multiple_of(8) && vec[expected - 1] & (0xff >> (len % 8)) != 0 {
+            return Err(Error::PaddingBits { num_verts });
+        }
+        Ok(BitVec { len, vec })
+    }
 }
 
 
@@ -56,4 +90,30 @@ mod test {
         assert!(bv.get(3) == true);
         assert!(bv.get(4) == false);
     }
+
+    #[test]
+    fn decoding() {
+        // 10 edges of a graph on 5 vertices, the last 6 bits of the second byte
+        // are padding
+        let bv = BitVec::from_base64("/8A", 5).unwrap();
+        assert_eq!((bv.len, bv.vec), (10, vec![0xff, 0xc0]));
+        assert_eq!(
+            BitVec::from_base64("/8E", 5).unwrap_err(),
+            Error::PaddingBits { num_verts: 5 }
+        );
+        assert_eq!(
+            BitVec::from_base64("/w", 5).unwrap_err(),
+            Error::WrongLength {
+                len: 1,
+                expected: 2,
+                num_verts: 5
+            }
+        );
+        assert!(matches!(BitVec::from_base64("/8?", 5), Err(Error::Base64(_))));
+        assert_eq!(BitVec::from_base64("", 1).unwrap().vec, vec![]);
+
+        // 79800 edges on 400 vertices, more than a u16 counts
+        let zeros = base64::encode_config(vec![0; 9975], base64::STANDARD_NO_PAD);
+        assert_eq!(BitVec::from_base64(&zeros, 400).unwrap().len, 79800);
+    }
 }
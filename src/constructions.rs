@@ -0,0 +1,273 @@
+// Known Ramsey colorings, as test fixtures and seeds for searches
+//
+// Every construction here is vertex transitive, red edges join the pairs whose
+// difference lies in a connection set
+
+use crate::error::Error;
+use crate::graph_like::GraphMut;
+
+/// A finite group on the elements 0..order, with 0 the identity
+pub trait Group {
+    fn order(&self) -> usize;
+
+    fn op(&self, a: usize, b: usize) -> usize;
+
+    fn inverse(&self, a: usize) -> usize;
+}
+
+/// The integers mod n
+#[derive(Clone, Copy, Debug)]
+pub struct Cyclic(pub usize);
+
+impl Group for Cyclic {
+    fn order(&self) -> usize {
+        self.0
+    }
+
+    fn op(&self, a: usize, b: usize) -> usize {
+        (a + b) % self.0
+    }
+
+    fn inverse(&self, a: usize) -> usize {
+        (self.0 - a) % self.0
+    }
+}
+
+/// The symmetries of an n-gon, r^k is k and r^k s is n + k
+#[derive(Clone, Copy, Debug)]
+pub struct Dihedral(pub usize);
+
+impl Group for Dihedral {
+    fn order(&self) -> usize {
+        2 * self.0
+    }
+
+    fn op(&self, a: usize, b: usize) -> usize {
+        let n = self.0;
+        let (k1, f1) = (a % n, a / n);
+        let (k2, f2) = (b % n, b / n);
+        // s r^k = r^-k s
+        let k = if f1 == 0 { k1 + k2 } else { k1 + n - k2 };
+        k % n + n * (f1 ^ f2)
+    }
+
+    fn inverse(&self, a: usize) -> usize {
+        if a < self.0 {
+            (self.0 - a) % self.0
+        } else {
+            a
+        }
+    }
+}
+
+/// The direct product, (a, b) is a + order(A) * b
+#[derive(Clone, Copy, Debug)]
+pub struct Product<A, B>(pub A, pub B);
+
+impl<A: Group, B: Group> Group for Product<A, B> {
+    fn order(&self) -> usize {
+        self.0.order() * self.1.order()
+    }
+
+    fn op(&self, a: usize, b: usize) -> usize {
+        let n = self.0.order();
+        self.0.op(a % n, b % n) + n * self.1.op(a / n, b / n)
+    }
+
+    fn inverse(&self, a: usize) -> usize {
+        let n = self.0.order();
+        self.0.inverse(a % n) + n * self.1.inverse(a / n)
+    }
+}
+
+/// The Cayley graph of group, a and b are joined in red when a^-1 b is in
+/// connection. The connection set has to be closed under inverses and must not
+/// contain the identity
+pub fn cayley<G: GraphMut, Gr: Group>(group: &Gr, connection: &[usize]) -> Result<G, Error> {
+    let n = group.order();
+    for &s in connection {
+        if s >= n {
+            return Err(Error::VertexOutOfRange {
+                vertex: s,
+                num_verts: n,
+            });
+        }
+        if s == 0 {
+            return Err(Error::SelfLoop(0));
+        }
+        if !connection.contains(&group.inverse(s)) {
+            return Err(Error::NotSymmetric(0, s));
+        }
+    }
+    let mut edges = vec![];
+    for a in 0..n {
+        for &s in connection {
+            edges.push((a, group.op(a, s)));
+        }
+    }
+    G::from_edges(n, &edges)
+}
+
+/// The circulant coloring on n vertices, i and j are joined in red when i - j or
+/// j - i is in connection mod n
+pub fn circulant<G: GraphMut>(n: usize, connection: &[usize]) -> Result<G, Error> {
+    let mut edges = vec![];
+    for i in 0..n {
+        for &d in connection {
+            edges.push((i, (i + d) % n));
+        }
+    }
+    G::from_edges(n, &edges)
+}
+
+/// The Paley graph on q vertices, joined in red when the difference is a
+/// nonzero square mod q. It is self complementary, so a good (s, s) seed.
+/// Only primes q = 1 mod 4 are supported
+pub fn paley<G: GraphMut>(q: usize) -> G {
+    assert!(
+        q > 1 && q % 4 == 1 && (2..q).take_while(|d| d * d <= q).all(|d| !q.is_multiple_of(d)),
+        "paley graphs need a prime q = 1 mod 4, got {}",
+        q
+    );
+    let squares: Vec<usize> = (1..q).map(|x| x * x % q).collect();
+    circulant(q, &squares).unwrap()
+}
+
+/// The unique largest coloring with no red K_s and no blue K_t, for the four
+/// nontrivial cases with s, t <= 5 where it is known and unique up to
+/// isomorphism: (3, 3) on 5, (3, 4) on 8, (3, 5) on 13 and (4, 4) on 17 vertices.
+/// With s > t the colors of the (t, s) coloring are swapped
+pub fn extremal<G: GraphMut>(s: usize, t: usize) -> Option<G> {
+    if s > t {
+        return extremal::<G>(t, s).map(|g| g.complement());
+    }
+    match (s, t) {
+        (3, 3) => circulant(5, &[1]).ok(),
+        (3, 4) => circulant(8, &[1, 4]).ok(),
+        (3, 5) => circulant(13, &[1, 5]).ok(),
+        (4, 4) => Some(paley(17)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+    use crate::graph_like::GraphLike;
+    use crate::labeler::Pipeline;
+    use crate::test_util::tier_up_to;
+
+    fn has_clique(g: &Graph, k: usize, col: bool) -> bool {
+        match k {
+            3 => g.has_k3(col),
+            4 => g.has_k4(col),
+            5 => g.has_k5(col),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn extremal_colorings() {
+        for &(s, t, n) in [(3, 3, 5), (3, 4, 8), (4, 3, 8), (3, 5, 13), (4, 4, 17)].iter() {
+            let g: Graph = extremal(s, t).unwrap();
+            assert_eq!(g.num_verts(), n);
+            assert!(!has_clique(&g, s, true), "red K{} in ({}, {})", s, s, t);
+            assert!(!has_clique(&g, t, false), "blue K{} in ({}, {})", t, s, t);
+            // adding any vertex creates one of the cliques
+            for h in g.get_next_size() {
+                assert!(h.has_kns(s as u32, t as u32));
+            }
+        }
+        assert!(extremal::<Graph>(4, 5).is_none());
+    }
+
+    #[test]
+    fn paley() {
+        let g: Graph = super::paley(13);
+        assert_eq!(g.label_degrees(true), vec![6; 13]);
+        assert_eq!(
+            g.to_edges(),
+            circulant::<Graph>(13, &[1, 3, 4]).unwrap().to_edges()
+        );
+        assert_eq!(
+            super::paley::<Graph>(5).to_edges(),
+            extremal::<Graph>(3, 3).unwrap().to_edges()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn paley_needs_prime() {
+        super::paley::<Graph>(9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn paley_needs_more_than_one_vertex() {
+        super::paley::<Graph>(1);
+    }
+
+    #[test]
+    fn groups() {
+        let groups: Vec<Box<dyn Group>> = vec![
+            Box::new(Cyclic(7)),
+            Box::new(Dihedral(5)),
+            Box::new(Product(Cyclic(2), Dihedral(3))),
+        ];
+        for group in groups.iter() {
+            let n = group.order();
+            for a in 0..n {
+                assert_eq!(group.op(a, 0), a);
+                assert_eq!(group.op(0, a), a);
+                assert_eq!(group.op(a, group.inverse(a)), 0);
+                for b in 0..n {
+                    for c in 0..n {
+                        assert_eq!(
+                            group.op(group.op(a, b), c),
+                            group.op(a, group.op(b, c))
+                        );
+                    }
+                }
+            }
+        }
+        // dihedral groups are not abelian
+        assert_ne!(Dihedral(5).op(1, 5), Dihedral(5).op(5, 1));
+    }
+
+    #[test]
+    fn cayley_graphs() {
+        let g: Graph = cayley(&Cyclic(8), &[1, 7, 4]).unwrap();
+        assert_eq!(g.to_edges(), extremal::<Graph>(3, 4).unwrap().to_edges());
+
+        // rotating by one either way or any of the four reflections
+        let d: Graph = cayley(&Dihedral(4), &[1, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(d.label_degrees(true), vec![6; 8]);
+        let p: Graph = cayley(&Product(Cyclic(3), Cyclic(3)), &[1, 2, 3, 6]).unwrap();
+        assert_eq!(p.label_degrees(true), vec![4; 9]);
+        assert!(!p.has_k4(true));
+
+        assert_eq!(
+            cayley::<Graph, _>(&Cyclic(8), &[1]).unwrap_err(),
+            Error::NotSymmetric(0, 1)
+        );
+        assert_eq!(
+            cayley::<Graph, _>(&Cyclic(8), &[0]).unwrap_err(),
+            Error::SelfLoop(0)
+        );
+        assert_eq!(
+            circulant::<Graph>(8, &[8]).unwrap_err(),
+            Error::SelfLoop(0)
+        );
+    }
+
+    #[test]
+    fn found_by_generation() {
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(8, &labeler);
+        // the (3, 4) coloring on 8 vertices has no K4 in either color
+        let g: Graph = extremal(3, 4).unwrap();
+        assert!(tier.find(&g, &labeler).is_some());
+        assert!(tier.find(&g.complement(), &labeler).is_some());
+    }
+}
@@ -0,0 +1,106 @@
+// Errors from building graphs out of user supplied data, and from reading and
+// writing them
+
+use std::fmt;
+use std::io;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// An edge from a vertex to itself
+    SelfLoop(usize),
+    /// A vertex index that is not below the number of vertices
+    VertexOutOfRange { vertex: usize, num_verts: usize },
+    /// A graph on more vertices than a representation holds, e.g. more than 64
+    /// on the bitboard backend or in adjacency rows
+    TooManyVertices { num_verts: usize, max: usize },
+    /// An adjacency matrix row whose length differs from the number of rows
+    NotSquare { row: usize, len: usize, num_verts: usize },
+    /// An adjacency matrix or row set with (u, v) and (v, u) disagreeing
+    NotSymmetric(usize, usize),
+    /// Reading or writing a file failed, io::Error is neither Clone nor Eq so
+    /// only its kind and message are kept
+    Io { kind: io::ErrorKind, message: String },
+    /// A graph that is not valid base64
+    Base64(base64::DecodeError),
+    /// An encoded graph whose edge bits take `len` bytes where a graph on
+    /// `num_verts` vertices takes `expected`, e.g. a truncated file
+    WrongLength { len: usize, expected: usize, num_verts: usize },
+    /// Bits set past the last edge of an encoded graph on `num_verts` vertices,
+    /// usually a graph of another size
+    PaddingBits { num_verts: usize },
+    /// A string that is not graph6
+    InvalidGraph6(String),
+    /// A vertex label in a labeled tier file that does not parse
+    InvalidLabel(String),
+    /// A labeler spec, format name or similar option that does not parse
+    InvalidSpec(String),
+    /// An error in line `.0` of a tier file, counting from 1
+    AtLine(usize, Box<Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SelfLoop(v) => write!(f, "self loop at vertex {}", v),
+            Error::VertexOutOfRange { vertex, num_verts } => write!(
+                f,
+                "vertex {} out of range for a graph on {} vertices",
+                vertex, num_verts
+            ),
+            Error::TooManyVertices { num_verts, max } => write!(
+                f,
+                "a graph on {} vertices, at most {} fit",
+                num_verts, max
+            ),
+            Error::NotSquare {
+                row,
+                len,
+                num_verts,
+            } => write!(
+                f,
+                "row {} has {} entries, expected {}",
+                row, len, num_verts
+            ),
+            Error::NotSymmetric(u, v) => {
+                write!(f, "edge ({}, {}) differs from edge ({}, {})", u, v, v, u)
+            }
+            Error::Io { message, .. } => write!(f, "{}", message),
+            Error::Base64(e) => write!(f, "invalid base64: {}", e),
+            Error::WrongLength {
+                len,
+                expected,
+                num_verts,
+            } => write!(
+                f,
+                "{} bytes of edges where a graph on {} vertices has {}",
+                len, num_verts, expected
+            ),
+            Error::PaddingBits { num_verts } => write!(
+                f,
+                "bits set past the last edge of a graph on {} vertices",
+                num_verts
+            ),
+            Error::InvalidGraph6(s) => write!(f, "invalid graph6 `{}`", s),
+            Error::InvalidLabel(s) => write!(f, "invalid vertex label `{}`", s),
+            Error::InvalidSpec(msg) => write!(f, "{}", msg),
+            Error::AtLine(line, e) => write!(f, "line {}: {}", line, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Base64(e)
+    }
+}
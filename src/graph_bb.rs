@@ -0,0 +1,396 @@
+// A graph stored as one u64 of red neighbours per vertex, at most 64 vertices
+//
+// Clique searches, degrees and breadth first searches work on whole rows with
+// AND and popcount instead of looking edges up one at a time
+
+use crate::bitvec::BitVec;
+use base64;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::error::Error;
+use crate::graph_like::{Extendable, GraphLike, GraphMut};
+use std::fmt;
+
+#[derive(Clone, Debug)]
+pub struct Graph {
+    num_verts: usize,
+    rows: Vec<u64>,
+}
+
+impl GraphLike for Graph {
+    fn num_verts(&self) -> usize {
+        self.num_verts
+    }
+
+    fn get_edge(&self, n: usize, m: usize) -> bool {
+        self.rows[n] >> m & 1 != 0
+    }
+
+    fn neighbourhood(&self, v: usize, col: bool) -> u64 {
+        if col {
+            self.rows[v]
+        } else {
+            !self.rows[v] & self.all() & !(1 << v)
+        }
+    }
+
+    fn has_k3(&self, col: bool) -> bool {
+        self.has_clique(self.all(), 3, col)
+    }
+
+    fn has_k3r(&self, col: bool) -> bool {
+        self.has_clique_on_last(3, col)
+    }
+
+    fn has_k4(&self, col: bool) -> bool {
+        self.has_clique(self.all(), 4, col)
+    }
+
+    fn has_k4r(&self, col: bool) -> bool {
+        self.has_clique_on_last(4, col)
+    }
+
+    fn has_k5(&self, col: bool) -> bool {
+        self.has_clique(self.all(), 5, col)
+    }
+
+    fn has_k5r(&self, col: bool) -> bool {
+        self.has_clique_on_last(5, col)
+    }
+
+    fn has_k6(&self, col: bool) -> bool {
+        self.has_clique(self.all(), 6, col)
+    }
+
+    fn has_k6r(&self, col: bool) -> bool {
+        self.has_clique_on_last(6, col)
+    }
+
+    fn has_k7(&self, col: bool) -> bool {
+        self.has_clique(self.all(), 7, col)
+    }
+
+    fn has_k7r(&self, col: bool) -> bool {
+        self.has_clique_on_last(7, col)
+    }
+
+    fn label_k3s(&self, col: bool) -> Vec<u32> {
+        self.label_cliques(3, col)
+    }
+
+    fn label_k4s(&self, col: bool) -> Vec<u32> {
+        self.label_cliques(4, col)
+    }
+
+    fn label_k5s(&self, col: bool) -> Vec<u32> {
+        self.label_cliques(5, col)
+    }
+
+    fn label_degrees(&self, col: bool) -> Vec<u32> {
+        (0..self.num_verts)
+            .map(|v| self.neighbourhood(v, col).count_ones())
+            .collect()
+    }
+
+    fn convolute(&self, labels: &mut [u32], buf: &mut Vec<Vec<(u32, u32)>>, col: bool) {
+        for sub_buf in buf.iter_mut() {
+            sub_buf.clear();
+        }
+        let rows: Vec<u64> = (0..self.num_verts)
+            .map(|v| self.neighbourhood(v, col))
+            .collect();
+        for (root, out) in buf.iter_mut().enumerate().take(self.num_verts) {
+            let mut visited = 1u64 << root;
+            let mut frontier = visited;
+            let mut curr_dist = 0u32;
+            while frontier != 0 {
+                curr_dist += 1;
+                let mut next = 0;
+                let mut f = frontier;
+                while f != 0 {
+                    next |= rows[f.trailing_zeros() as usize];
+                    f &= f - 1;
+                }
+                frontier = next & !visited;
+                visited |= frontier;
+                let mut f = frontier;
+                while f != 0 {
+                    out.push((curr_dist, labels[f.trailing_zeros() as usize]));
+                    f &= f - 1;
+                }
+            }
+        }
+        for i in 0..self.num_verts {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u32(labels[i]); //previous label, to guarantee no worse labeling
+            buf[i].sort();
+            buf[i].hash(&mut hasher);
+            labels[i] = hasher.finish() as u32
+        }
+    }
+}
+
+impl Extendable for Graph {
+    fn get_next_size(&self) -> Vec<Graph> {
+        Graph::get_next_size(self)
+    }
+}
+
+impl GraphMut for Graph {
+    const MAX_VERTS: usize = 64;
+
+    fn empty(n: usize) -> Self {
+        Graph::new(n)
+    }
+
+    fn set_edge(&mut self, n: usize, m: usize, val: bool) {
+        assert_ne!(n, m, "graphs have no loops");
+        if val {
+            self.rows[n] |= 1 << m;
+            self.rows[m] |= 1 << n;
+        } else {
+            self.rows[n] &= !(1 << m);
+            self.rows[m] &= !(1 << n);
+        }
+    }
+
+    fn add_vertex(&mut self, mask: u64) {
+        assert!(self.num_verts < 64, "the bitboard backend holds at most 64 vertices");
+        let mask = mask & self.all();
+        for (k, row) in self.rows.iter_mut().enumerate() {
+            *row |= (mask >> k & 1) << self.num_verts;
+        }
+        self.rows.push(mask);
+        self.num_verts += 1;
+    }
+
+    fn remove_vertex(&mut self, v: usize) {
+        let low = (1u64 << v) - 1;
+        self.rows.remove(v);
+        for row in self.rows.iter_mut() {
+            *row = *row & low | (*row >> 1) & !low;
+        }
+        self.num_verts -= 1;
+    }
+}
+
+impl Graph {
+    pub fn new(n: usize) -> Self {
+        assert!(n <= 64, "the bitboard backend holds at most 64 vertices");
+        Graph {
+            num_verts: n,
+            rows: vec![0; n],
+        }
+    }
+
+    fn all(&self) -> u64 {
+        if self.num_verts == 64 {
+            !0
+        } else {
+            (1 << self.num_verts) - 1
+        }
+    }
+
+    // whether the vertices in cand contain a k-clique of color col
+    fn has_clique(&self, cand: u64, k: u32, col: bool) -> bool {
+        if k == 0 {
+            return true;
+        }
+        if cand.count_ones() < k {
+            return false;
+        }
+        let mut c = cand;
+        while c != 0 {
+            let v = c.trailing_zeros() as usize;
+            c &= c - 1;
+            //only look at later vertices so every clique is tried once
+            if self.has_clique(c & self.neighbourhood(v, col), k - 1, col) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn count_cliques(&self, cand: u64, k: u32, col: bool) -> u32 {
+        if k == 0 {
+            return 1;
+        }
+        if k == 1 {
+            return cand.count_ones();
+        }
+        let mut count = 0;
+        let mut c = cand;
+        while c != 0 {
+            let v = c.trailing_zeros() as usize;
+            c &= c - 1;
+            count += self.count_cliques(c & self.neighbourhood(v, col), k - 1, col);
+        }
+        count
+    }
+
+    fn has_clique_on_last(&self, k: u32, col: bool) -> bool {
+        if self.num_verts < k as usize {
+            return false;
+        }
+        let last = self.num_verts - 1;
+        self.has_clique(self.neighbourhood(last, col), k - 1, col)
+    }
+
+    fn label_cliques(&self, k: u32, col: bool) -> Vec<u32> {
+        (0..self.num_verts)
+            .map(|v| self.count_cliques(self.neighbourhood(v, col), k - 1, col))
+            .collect()
+    }
+
+    pub fn get_next_size(&self) -> Vec<Graph> {
+        let mut next_size = Vec::with_capacity(1 << self.num_verts);
+        for mask in 0..(1u64 << self.num_verts) {
+            let mut g = self.clone();
+            g.num_verts += 1;
+            for (i, row) in g.rows.iter_mut().enumerate() {
+                *row |= (mask >> i & 1) << self.num_verts;
+            }
+            g.rows.push(mask);
+            next_size.push(g);
+        }
+        next_size
+    }
+
+    // the triangular edge list the other backends store
+    fn to_bitvec(&self) -> BitVec {
+        let mut bits = BitVec::new();
+        for m in 0..self.num_verts {
+            for n in 0..m {
+                bits.push(self.get_edge(n, m));
+            }
+        }
+        bits
+    }
+
+    pub fn to_string_append(&self, buf: &mut String) {
+        base64::encode_config_buf(&self.to_bitvec().vec, base64::STANDARD_NO_PAD, buf);
+    }
+
+    // decode base 64, num_verts is not stored so it has to match the encoding
+    pub fn from_str(s: &str, num_verts: usize) -> Result<Graph, Error> {
+        if num_verts > Self::MAX_VERTS {
+            return Err(Error::TooManyVertices {
+                num_verts,
+                max: Self::MAX_VERTS,
+            });
+        }
+        let bits = BitVec::from_base64(s, num_verts)?;
+        let mut g = Graph::new(num_verts);
+        let mut i = 0;
+        for m in 0..num_verts {
+            for n in 0..m {
+                if bits.get(i) {
+                    g.rows[n] |= 1 << m;
+                    g.rows[m] |= 1 << n;
+                }
+                i += 1;
+            }
+        }
+        Ok(g)
+    }
+}
+
+// base 64 encoding of bits of edges, the same as the other backends
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buf = String::new();
+        self.to_string_append(&mut buf);
+        f.write_str(&buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+
+    // only get_edge, so every algorithm falls back to the scalar defaults
+    struct Scalar<'a>(&'a Graph);
+
+    impl<'a> GraphLike for Scalar<'a> {
+        fn get_edge(&self, n: usize, m: usize) -> bool {
+            self.0.get_edge(n, m)
+        }
+
+        fn num_verts(&self) -> usize {
+            self.0.num_verts()
+        }
+    }
+
+    // a few hundred pseudo random graphs on up to 11 vertices
+    fn graphs() -> Vec<Graph> {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        (0..300)
+            .map(|_| {
+                let n = 2 + rng.below(10);
+                rng.graph(n)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_scalar() {
+        for g in graphs().iter() {
+            let s = Scalar(g);
+            for &col in [true, false].iter() {
+                assert_eq!(g.has_k3(col), s.has_k3(col));
+                assert_eq!(g.has_k4(col), s.has_k4(col));
+                assert_eq!(g.has_k5(col), s.has_k5(col));
+                assert_eq!(g.has_k3r(col), s.has_k3r(col));
+                assert_eq!(g.has_k4r(col), s.has_k4r(col));
+                assert_eq!(g.has_k5r(col), s.has_k5r(col));
+                assert_eq!(g.has_k6r(col), s.has_k6r(col));
+                assert_eq!(g.label_degrees(col), s.label_degrees(col));
+                assert_eq!(g.label_k3s(col), s.label_k3s(col));
+                assert_eq!(g.label_k4s(col), s.label_k4s(col));
+                assert_eq!(g.label_k5s(col), s.label_k5s(col));
+                for v in 0..g.num_verts() {
+                    assert_eq!(g.neighbourhood(v, col), s.neighbourhood(v, col));
+                }
+
+                let n = g.num_verts();
+                let mut fast = (0..n as u32).map(|v| v % 3).collect::<Vec<_>>();
+                let mut slow = fast.clone();
+                g.convolute(&mut fast, &mut vec![vec![]; n], col);
+                s.convolute(&mut slow, &mut vec![vec![]; n], col);
+                assert_eq!(fast, slow);
+            }
+        }
+    }
+
+    #[test]
+    fn remove_vertex() {
+        for g in graphs().iter() {
+            for v in 0..g.num_verts() {
+                let mut h = g.clone();
+                h.remove_vertex(v);
+                let old: Vec<usize> = (0..g.num_verts()).filter(|u| *u != v).collect();
+                for a in 0..h.num_verts() {
+                    for b in 0..h.num_verts() {
+                        if a != b {
+                            assert_eq!(h.get_edge(a, b), g.get_edge(old[a], old[b]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn too_many_vertices() {
+        let err = Error::TooManyVertices {
+            num_verts: 70,
+            max: 64,
+        };
+        // 2415 zero edge bits on 70 vertices
+        assert_eq!(Graph::from_str(&"A".repeat(403), 70).unwrap_err(), err);
+        let g6 = format!("~?@E{}", "?".repeat(403));
+        assert_eq!(crate::io::from_graph6::<Graph>(&g6).unwrap_err(), err);
+    }
+}
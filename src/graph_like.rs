@@ -1,3 +1,175 @@
+use crate::error::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Graphs that can be grown one vertex at a time, which is all a `Tier` needs
+/// besides `GraphLike` to generate the next size
+pub trait Extendable: Sized {
+    /// Every graph on one more vertex that has this graph as its first `num_verts`
+    /// vertices
+    fn get_next_size(&self) -> Vec<Self>;
+}
+
+/// Graphs that can be edited in place, for constructions and local search
+///
+/// Only the first four methods touch the representation, everything else is
+/// built on them
+pub trait GraphMut: GraphLike + Sized {
+    /// The most vertices a graph of this type holds, `empty` panics above it
+    const MAX_VERTS: usize = usize::MAX;
+
+    /// The graph on n vertices with every edge blue
+    fn empty(n: usize) -> Self;
+
+    /// Colors the edge between n and m red if val is true, blue otherwise
+    fn set_edge(&mut self, n: usize, m: usize, val: bool);
+
+    /// Appends a vertex joined in red to the vertices set in mask and in blue to
+    /// the rest, so the graph can have at most 64 vertices beforehand
+    fn add_vertex(&mut self, mask: u64);
+
+    /// Removes vertex v, the vertices after it shift down by one
+    fn remove_vertex(&mut self, v: usize);
+
+    fn flip_edge(&mut self, n: usize, m: usize) {
+        let e = self.get_edge(n, m);
+        self.set_edge(n, m, !e);
+    }
+
+    /// The graph on the distinct vertices verts, vertex i of it is verts[i] here
+    fn induced_subgraph(&self, verts: &[usize]) -> Self {
+        let mut out = Self::empty(verts.len());
+        for (i, &a) in verts.iter().enumerate() {
+            for (j, &b) in verts.iter().enumerate().skip(i + 1) {
+                if self.get_edge(a, b) {
+                    out.set_edge(i, j, true);
+                }
+            }
+        }
+        out
+    }
+
+    /// The graph on n vertices whose red edges are exactly edges, listing an
+    /// edge twice or in both directions is fine
+    fn from_edges(n: usize, edges: &[(usize, usize)]) -> Result<Self, Error> {
+        let mut out = Self::empty(n);
+        for &(u, v) in edges {
+            for &vertex in [u, v].iter() {
+                if vertex >= n {
+                    return Err(Error::VertexOutOfRange {
+                        vertex,
+                        num_verts: n,
+                    });
+                }
+            }
+            if u == v {
+                return Err(Error::SelfLoop(u));
+            }
+            out.set_edge(u, v, true);
+        }
+        Ok(out)
+    }
+
+    /// The graph with a red edge wherever matrix is true, the matrix has to be
+    /// square and symmetric with a false diagonal
+    fn from_adjacency_matrix(matrix: &[Vec<bool>]) -> Result<Self, Error> {
+        let n = matrix.len();
+        for (row, entries) in matrix.iter().enumerate() {
+            if entries.len() != n {
+                return Err(Error::NotSquare {
+                    row,
+                    len: entries.len(),
+                    num_verts: n,
+                });
+            }
+        }
+        let mut out = Self::empty(n);
+        for (u, entries) in matrix.iter().enumerate() {
+            if entries[u] {
+                return Err(Error::SelfLoop(u));
+            }
+            for (v, other) in matrix.iter().enumerate().skip(u + 1) {
+                if entries[v] != other[u] {
+                    return Err(Error::NotSymmetric(u, v));
+                }
+                out.set_edge(u, v, entries[v]);
+            }
+        }
+        Ok(out)
+    }
+
+    /// The graph whose vertex v is joined in red to the vertices set in rows[v],
+    /// at most 64 vertices, see `GraphLike::neighbourhood`
+    fn from_adjacency_rows(rows: &[u64]) -> Result<Self, Error> {
+        let n = rows.len();
+        if n > 64 {
+            return Err(Error::TooManyVertices {
+                num_verts: n,
+                max: 64,
+            });
+        }
+        let mut out = Self::empty(n);
+        for (u, row) in rows.iter().enumerate() {
+            if n < 64 && row >> n != 0 {
+                return Err(Error::VertexOutOfRange {
+                    vertex: 63 - row.leading_zeros() as usize,
+                    num_verts: n,
+                });
+            }
+            if row >> u & 1 != 0 {
+                return Err(Error::SelfLoop(u));
+            }
+            for (v, other) in rows.iter().enumerate().skip(u + 1) {
+                if row >> v & 1 != other >> u & 1 {
+                    return Err(Error::NotSymmetric(u, v));
+                }
+                out.set_edge(u, v, row >> v & 1 != 0);
+            }
+        }
+        Ok(out)
+    }
+
+    /// The same graph with red and blue swapped
+    fn complement(&self) -> Self {
+        let n = self.num_verts();
+        let mut out = Self::empty(n);
+        for m in 0..n {
+            for k in 0..m {
+                out.set_edge(k, m, !self.get_edge(k, m));
+            }
+        }
+        out
+    }
+
+    /// This graph followed by other, every edge between the two is blue
+    fn disjoint_union(&self, other: &Self) -> Self {
+        let n = self.num_verts();
+        let mut out = Self::empty(n + other.num_verts());
+        for m in 0..n {
+            for k in 0..m {
+                out.set_edge(k, m, self.get_edge(k, m));
+            }
+        }
+        for m in 0..other.num_verts() {
+            for k in 0..m {
+                out.set_edge(n + k, n + m, other.get_edge(k, m));
+            }
+        }
+        out
+    }
+
+    /// This graph followed by other, every edge between the two is red
+    fn join(&self, other: &Self) -> Self {
+        let mut out = self.disjoint_union(other);
+        for k in 0..self.num_verts() {
+            for m in 0..other.num_verts() {
+                out.set_edge(k, self.num_verts() + m, true);
+            }
+        }
+        out
+    }
+}
+
 pub trait GraphLike {
     /// Whether there is an edge between vertices n and m
     fn get_edge(&self, n: usize, m: usize) -> bool;
@@ -5,6 +177,55 @@ pub trait GraphLike {
     /// The number of vertices this graph has
     fn num_verts(&self) -> usize;
 
+    /// The red edges (u, v) with u < v, ordered by v and then u like the base64
+    /// encoding
+    fn to_edges(&self) -> Vec<(usize, usize)> {
+        let mut out = vec![];
+        for m in 0..self.num_verts() {
+            for k in 0..m {
+                if self.get_edge(k, m) {
+                    out.push((k, m));
+                }
+            }
+        }
+        out
+    }
+
+    /// The adjacency matrix of the red graph
+    fn to_adjacency_matrix(&self) -> Vec<Vec<bool>> {
+        let n = self.num_verts();
+        (0..n)
+            .map(|u| (0..n).map(|v| u != v && self.get_edge(u, v)).collect())
+            .collect()
+    }
+
+    /// `neighbourhood` of every vertex in red, the inverse of
+    /// `GraphMut::from_adjacency_rows`
+    fn to_adjacency_rows(&self) -> Vec<u64> {
+        (0..self.num_verts())
+            .map(|v| self.neighbourhood(v, true))
+            .collect()
+    }
+
+    /// The color of the edge between n and m, this is what the isomorphism check
+    /// compares. Graphs with more than two colors should override it
+    fn edge_color(&self, n: usize, m: usize) -> u8 {
+        self.get_edge(n, m) as u8
+    }
+
+    /// Bitmask of the vertices joined to v in color col, only for graphs of at most
+    /// 64 vertices. Backends that store adjacency rows should override this
+    fn neighbourhood(&self, v: usize, col: bool) -> u64 {
+        assert!(self.num_verts() <= 64, "neighbourhood masks need at most 64 vertices");
+        let mut mask = 0;
+        for u in 0..self.num_verts() {
+            if u != v && self.get_edge(u, v) == col {
+                mask |= 1 << u;
+            }
+        }
+        mask
+    }
+
         //true means connected, false means disconnected
     fn has_k3(&self, col: bool) -> bool {
         if self.num_verts() < 3 {
@@ -369,4 +590,225 @@ pub trait GraphLike {
         }
     }
 
+    //counts red k3's that each vertex is a part of
+    fn label_k3s(&self, col: bool) -> Vec<u32> {
+        let mut ans = Vec::with_capacity(self.num_verts());
+
+        for v in 0..self.num_verts() {
+            let mut count = 0u32;
+            for a in 0..self.num_verts() {
+                if a != v && (self.get_edge(v, a) == col) {
+                    for b in a + 1..self.num_verts() {
+                        if b != v
+                            && b != a
+                            && (self.get_edge(v, b) == col)
+                            && (self.get_edge(a, b) == col)
+                        {
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            ans.push(count);
+        }
+        ans
+    }
+
+    //counts red k4's that each vertex is a part of
+    fn label_k4s(&self, col: bool) -> Vec<u32> {
+        let mut ans = Vec::with_capacity(self.num_verts());
+
+        for v in 0..self.num_verts() {
+            let mut count = 0u32;
+            for a in 0..self.num_verts() {
+                if a != v && (self.get_edge(v, a) == col) {
+                    for b in a + 1..self.num_verts() {
+                        if b != v
+                            && b != a
+                            && (self.get_edge(v, b) == col)
+                            && (self.get_edge(a, b) == col)
+                        {
+                            for c in b + 1..self.num_verts() {
+                                if v != c
+                                    && a != c
+                                    && b != c
+                                    && (self.get_edge(v, c) == col)
+                                    && (self.get_edge(a, c) == col)
+                                    && (self.get_edge(b, c) == col)
+                                {
+                                    count += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ans.push(count);
+        }
+        ans
+    }
+
+    //counts red k4's that each vertex is a part of
+    fn label_k5s(&self, col: bool) -> Vec<u32> {
+        let mut ans = Vec::with_capacity(self.num_verts());
+
+        for v in 0..self.num_verts() {
+            let mut count = 0u32;
+            for a in 0..self.num_verts() {
+                if a != v && (self.get_edge(v, a) == col) {
+                    for b in a + 1..self.num_verts() {
+                        if b != v
+                            && b != a
+                            && (self.get_edge(v, b) == col)
+                            && (self.get_edge(a, b) == col)
+                        {
+                            for c in b + 1..self.num_verts() {
+                                if v != c
+                                    && a != c
+                                    && b != c
+                                    && (self.get_edge(v, c) == col)
+                                    && (self.get_edge(a, c) == col)
+                                    && (self.get_edge(b, c) == col)
+                                {
+                                    for d in c + 1..self.num_verts() {
+                                        if v != d
+                                            && d != a
+                                            && d != b
+                                            && d != c
+                                            && (self.get_edge(v, d) == col)
+                                            && (self.get_edge(a, d) == col)
+                                            && (self.get_edge(b, d) == col)
+                                            && (self.get_edge(c, d) == col)
+                                        {
+                                            count += 1;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ans.push(count);
+        }
+        ans
+    }
+
+    //shortest loops of color col through each pair of col edges at a vertex
+    fn label_polygons(&self, col: bool) -> Vec<u32> {
+        if self.num_verts() < 3 {
+            return vec!(0; self.num_verts());
+        }
+        let mut out = Vec::new();
+        use std::collections::VecDeque;
+        for root in 0..self.num_verts() {
+            let mut loops = vec![0; self.num_verts()];
+            for left in 0..self.num_verts() - 1 {
+                if left == root || self.get_edge(root, left) != col {
+                    continue;
+                }
+                for right in left + 1..self.num_verts() {
+                    if right == root || self.get_edge(root, right) != col {
+                        continue;
+                    }
+                    //left and right are antennae, we are counting the shortest
+                    //loop that connects them, if it exists
+
+                    //to do this we do a breadth first search looking for right, starting from left
+                    let mut length = 2;
+                    let mut visited = vec![false; self.num_verts()];
+                    visited[root] = true;
+                    visited[left] = true;
+                    visited[right] = true;
+                    let mut to_check = VecDeque::with_capacity(self.num_verts());
+                    to_check.push_back((left, 1));
+                    'a: loop {
+                        match to_check.pop_front() {
+                            Some((current, dist)) => {
+                                if self.get_edge(current, right) == col {
+                                    //+2 for this edge plus edge from right to root
+                                    length = dist + 2;
+                                    break 'a;
+                                }
+                                //no loop yet
+                                for i in 0..self.num_verts() {
+                                    if visited[i] || i == current || self.get_edge(current, i) != col {
+                                        continue;
+                                    }
+                                    to_check.push_back((i, dist + 1));
+                                    visited[i] = true;
+                                }
+                            }
+                            None => break 'a,
+                        }
+                    }
+                    loops[length - 2] += 1;
+                }
+            }
+
+            let mut hasher = DefaultHasher::new();
+            hasher.write(&loops);
+            out.push(hasher.finish() as u32);
+        }
+
+        out
+    }
+
+    //number of edges of color col at each vertex
+    fn label_degrees(&self, col: bool) -> Vec<u32> {
+        let mut d: Vec<u32> = Vec::with_capacity(self.num_verts());
+        for i in 0..self.num_verts() {
+            let mut k = 0;
+            for j in 0..self.num_verts() {
+                if i != j && self.get_edge(i, j) == col {
+                    k += 1;
+                }
+            }
+            d.push(k);
+        }
+        d
+    }
+
+    // This function takes an initial labeling, and produces a potentially better one(in place)
+    // the trees are grown along edges of color col
+    fn convolute(&self, labels: &mut [u32], buf: &mut Vec<Vec<(u32, u32)>>, col: bool) {
+        for sub_buf in buf.iter_mut() {
+            sub_buf.clear();
+        }
+        //kruskals for each vertex
+        for root in 0..self.num_verts() {
+            let current_tree = &mut buf[root];
+            let mut visited = vec![false; self.num_verts()];
+            visited[root] = true;
+            let mut curr_dist = 0u32;
+            loop {
+                curr_dist += 1;
+                let prev_visited = visited.clone();
+                let mut to_break = true;
+                for (i, vi) in prev_visited.iter().enumerate() {
+                    if *vi {
+                        for (j, vj) in visited.iter_mut().enumerate() {
+                            if i != j && !*vj {
+                                if self.get_edge(i, j) == col {
+                                    *vj = true;
+                                    to_break = false;
+                                    current_tree.push((curr_dist, labels[j]));
+                                }
+                            }
+                        }
+                    }
+                }
+                if to_break {
+                    break;
+                }
+            }
+        }
+        for i in 0..self.num_verts() {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u32(labels[i]); //previous label, to guarantee no worse labeling
+            buf[i].sort();
+            buf[i].hash(&mut hasher);
+            labels[i] = hasher.finish() as u32
+        }
+    }
 }
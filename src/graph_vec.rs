@@ -3,12 +3,14 @@
 use base64;
 use crate::bitvec::*;
 
-use crate::graph_like::GraphLike;
+use crate::error::Error;
+use crate::graph_like::{Extendable, GraphLike, GraphMut};
+use std::fmt;
 
 #[derive(Clone, Debug)]
 pub struct Graph {
-    pub num_verts: usize,
-    pub edges: Vec<bool>,
+    num_verts: usize,
+    edges: Vec<bool>,
 }
 
 impl GraphLike for Graph {
@@ -25,17 +27,52 @@ impl GraphLike for Graph {
 }
 
 
-impl Graph {
-    pub fn new(n: usize) -> Self {
-        let num_edges = n * (n - 1) / 2;
-        let mut e = Vec::with_capacity(num_edges);
-        for _ in 0..num_edges {
-            e.push(false);
+impl Extendable for Graph {
+    fn get_next_size(&self) -> Vec<Graph> {
+        Graph::get_next_size(self)
+    }
+}
+
+impl GraphMut for Graph {
+    fn empty(n: usize) -> Self {
+        Graph::new(n)
+    }
+
+    fn set_edge(&mut self, n: usize, m: usize, val: bool) {
+        assert_ne!(n, m, "graphs have no loops");
+        let n1 = m.min(n);
+        let m1 = m.max(n);
+        let i = (m1 * m1 - m1) / 2 + n1;
+        self.edges[i] = val;
+    }
+
+    fn add_vertex(&mut self, mask: u64) {
+        assert!(self.num_verts <= 64, "add_vertex masks only cover 64 vertices");
+        for k in 0..self.num_verts {
+            self.edges.push(mask >> k & 1 != 0);
         }
+        self.num_verts += 1;
+    }
 
+    fn remove_vertex(&mut self, v: usize) {
+        let mut edges = Vec::with_capacity(self.edges.len());
+        for m in 0..self.num_verts {
+            for n in 0..m {
+                if m != v && n != v {
+                    edges.push(self.get_edge(n, m));
+                }
+            }
+        }
+        self.num_verts -= 1;
+        self.edges = edges;
+    }
+}
+
+impl Graph {
+    pub fn new(n: usize) -> Self {
         Graph {
             num_verts: n,
-            edges: e,
+            edges: vec![false; n * n.saturating_sub(1) / 2],
         }
     }
 
@@ -54,15 +91,6 @@ impl Graph {
         next_size
     }
 
-    // base 64 encoding of bits of edges
-    pub fn to_string(&self) -> String {
-        let mut bitvec = BitVec::new();
-        for edge in self.edges.iter() {
-            bitvec.push(*edge);
-        }
-        base64::encode_config(&bitvec.vec, base64::STANDARD_NO_PAD)
-    }
-
     pub fn to_string_append(&self, buf: &mut String) {
         let mut bitvec = BitVec::new();
         for edge in self.edges.iter() {
@@ -71,117 +99,21 @@ impl Graph {
         base64::encode_config_buf(&bitvec.vec, base64::STANDARD_NO_PAD, buf);
     }
 
-    // decode base 64, size hint needed for space efficiency
-    pub fn from_str(s: &str, num_verts: usize) -> Result<Graph, Box<dyn std::error::Error>> {
-        let bits = base64::decode(s)?;
-
-        let mut edges = Vec::new();
-        let num_edges = num_verts * (num_verts - 1) / 2;
-
-        for bit_idx in 0..num_edges {
-            let bit = bit_idx % 8;
-            let byte = bit_idx / 8;
-            edges.push(bits[byte] & (1 << bit) > 0);
-        }
+    // decode base 64, num_verts is not stored so it has to match the encoding
+    pub fn from_str(s: &str, num_verts: usize) -> Result<Graph, Error> {
+        let bits = BitVec::from_base64(s, num_verts)?;
+        let edges = (0..bits.len).map(|i| bits.get(i)).collect();
         Ok(Graph { num_verts, edges })
     }
-
-    pub fn label_neighbors(&self) -> Vec<u32> {
-        let mut l = Vec::new();
-        for i in 0..self.num_verts {
-            let mut k = 0;
-            for j in 0..self.num_verts {
-                if self.get_edge(i as usize, j as usize) {
-                    k += 1;
-                }
-            }
-            l.push(k);
-        }
-        l
-    }
-
-    //counts red k3's that each vertex is a part of
-    pub fn label_k3s(&self) -> Vec<u32> {
-        let mut ans = Vec::with_capacity(self.num_verts);
-
-        for v in 0..self.num_verts {
-            let mut count = 0u32;
-            for a in 0..self.num_verts {
-                if a != v && self.get_edge(v, a) {
-                    for b in a + 1..self.num_verts {
-                        if b != v && b != a && self.get_edge(v, b) && self.get_edge(a, b) {
-                            count += 1;
-                        }
-                    }
-                }
-            }
-            ans.push(count);
-        }
-        ans
-    }
-    //TODO LazyStatic this guy
-    //    const FULLY_CONNECTED_VERTEX_LABEL: u32 =
-    pub fn label(&self) -> Vec<u32> {
-        if self.num_verts < 2 {
-            return vec![0];
-        }
-        let mut l: Vec<u32> = Vec::new();
-        for i in 0..self.num_verts {
-            let mut k = 0;
-            for j in 0..self.num_verts {
-                if i != j && self.get_edge(i as usize, j as usize) {
-                    k += 1;
-                }
-            }
-            l.push(k);
-        }
-        let mut m: Vec<u32> = Vec::new();
-        for i in 0..self.num_verts {
-            let mut k = 0;
-            for j in 0..self.num_verts {
-                if i != j && self.get_edge(i as usize, j as usize) {
-                    k += l[j as usize];
-                }
-            }
-            m.push(k);
-        }
-        let t = self.label_k3s();
-        let mut n: Vec<u32> = Vec::new();
-        for ((x, y), z) in l.into_iter().zip(m).zip(t) {
-            n.push((1 << 31) as u32 + (x << 20) as u32 + (y << 8) as u32 + z);
-        }
-        /*
-        let mut copy = n.clone();
-        copy.sort();
-        let comp = compute_complexity(&copy);
-        if comp > 1000 {
-            let t = self.label_k3s();
-            for (x, y) in n.iter_mut().zip(t) {
-                *x += y;
-            }
-        }*/
-        n
-    }
-
 }
 
-use crate::util;
-fn compute_complexity(labeling_sorted: &[u32]) -> u64 {
-    let mut comp = 1;
-    let mut current = labeling_sorted[0];
-    let mut cur_streak = 1;
-    for i in 1..labeling_sorted.len() {
-        if labeling_sorted[i] == current {
-            cur_streak += 1;
-        } else {
-            current = labeling_sorted[i];
-            comp *= util::factorial(&cur_streak);
-            cur_streak = 1;
-        }
+// base 64 encoding of bits of edges
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buf = String::new();
+        self.to_string_append(&mut buf);
+        f.write_str(&buf)
     }
-    comp *= util::factorial(&cur_streak);
-    //println!("{} {:?}", comp, labeling_sorted);
-    comp
 }
 
 #[cfg(test)]
@@ -195,33 +127,29 @@ mod tests {
         assert_eq!(next_size.len(), 4);
     }
 
+    fn edges() -> Vec<bool> {
+        let byte = [true, true, false, true, false, false, true, true];
+        byte.iter().cycle().take(45).cloned().collect()
+    }
+
     #[test]
     fn serialize() {
-        let edges = vec![0b1101_0011; 6];
-        let edges = BitVec {
-            len: 45,
-            vec: edges,
-        };
         let graph = Graph {
             num_verts: 10,
-            edges,
+            edges: edges(),
         };
-        assert_eq!(graph.to_string(), "09PT09PT");
+        //the 3 bits past the last edge are always zero
+        assert_eq!(graph.to_string(), "09PT09PQ");
     }
 
     #[test]
     fn round_trip() {
-        let edges = vec![0b1101_0011; 6];
-        let edges = BitVec {
-            len: 45,
-            vec: edges,
-        };
         let graph = Graph {
             num_verts: 10,
-            edges,
+            edges: edges(),
         };
         let ser = graph.to_string();
         let des = Graph::from_str(&ser, 10).unwrap();
-        assert_eq!(graph.edges.vec, des.edges.vec);
+        assert_eq!(graph.edges, des.edges);
     }
 }
@@ -0,0 +1,390 @@
+// Isomorphism invariants of colorings, for labeling and for analysing tiers
+
+use crate::graph_like::GraphLike;
+use std::collections::HashMap;
+
+/// Number of orbits a vertex can occupy in a 2-colored triangle
+pub const THREE_ORBITS: usize = 6;
+/// Number of orbits a vertex can occupy in a 2-colored K4
+pub const FOUR_ORBITS: usize = 20;
+
+/// How many induced 3 and 4 vertex subgraphs a vertex is part of, split by the
+/// orbit it occupies in them
+///
+/// A 2-coloring of a complete graph is determined by its red edges, so the orbits
+/// are those of graphs on 3 and 4 vertices. For `three` the orbit of v in {v, a, b}
+/// is `2 * (red edges at v) + (ab is red)`. For `four` orbits are numbered in order
+/// of the smallest edge code of a subgraph with v as its first vertex, edges
+/// ordered va, vb, vc, ab, ac, bc from the lowest bit up
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphletCounts {
+    pub three: [u32; THREE_ORBITS],
+    pub four: [u32; FOUR_ORBITS],
+}
+
+// red neighbourhood of every vertex as a bitmask
+fn red_rows<G: GraphLike + ?Sized>(g: &G) -> Vec<u64> {
+    (0..g.num_verts()).map(|v| g.neighbourhood(v, true)).collect()
+}
+
+// maps every edge code of a 4 vertex graph with a marked first vertex to its orbit
+fn four_orbit_table() -> [u8; 64] {
+    //bit of the edge between positions i < j
+    const BIT: [[u8; 4]; 4] = [[0, 0, 1, 2], [0, 0, 3, 4], [1, 3, 0, 5], [2, 4, 5, 0]];
+    const PERMS: [[usize; 4]; 6] = [
+        [0, 1, 2, 3],
+        [0, 1, 3, 2],
+        [0, 2, 1, 3],
+        [0, 2, 3, 1],
+        [0, 3, 1, 2],
+        [0, 3, 2, 1],
+    ];
+    let mut canon = [0u8; 64];
+    for code in 0..64u8 {
+        canon[code as usize] = PERMS
+            .iter()
+            .map(|p| {
+                let mut permuted = 0u8;
+                for i in 0..4 {
+                    for j in i + 1..4 {
+                        if code & (1 << BIT[i][j]) != 0 {
+                            permuted |= 1 << BIT[p[i]][p[j]];
+                        }
+                    }
+                }
+                permuted
+            })
+            .min()
+            .unwrap();
+    }
+    let mut reps: Vec<u8> = canon.to_vec();
+    reps.sort();
+    reps.dedup();
+    debug_assert_eq!(reps.len(), FOUR_ORBITS);
+    let mut table = [0u8; 64];
+    for code in 0..64 {
+        table[code] = reps.binary_search(&canon[code]).unwrap() as u8;
+    }
+    table
+}
+
+fn choose2(k: u32) -> u32 {
+    k * k.saturating_sub(1) / 2
+}
+
+/// The graphlet census of every vertex, see `GraphletCounts`
+pub fn graphlet_census<G: GraphLike + ?Sized>(g: &G) -> Vec<GraphletCounts> {
+    let n = g.num_verts();
+    let rows = red_rows(g);
+    let table = four_orbit_table();
+    let all = if n == 64 { !0 } else { (1u64 << n) - 1 };
+    let mut out = Vec::with_capacity(n);
+    for v in 0..n {
+        let mut counts = GraphletCounts::default();
+        let red = rows[v];
+        let blue = all & !red & !(1 << v);
+
+        //triples only need edge counts within and between the two neighbourhoods
+        let (mut rr, mut bb, mut rb) = (0, 0, 0);
+        let mut m = red;
+        while m != 0 {
+            let a = m.trailing_zeros() as usize;
+            m &= m - 1;
+            rr += (rows[a] & red).count_ones();
+            rb += (rows[a] & blue).count_ones();
+        }
+        let mut m = blue;
+        while m != 0 {
+            let a = m.trailing_zeros() as usize;
+            m &= m - 1;
+            bb += (rows[a] & blue).count_ones();
+        }
+        let (rr, bb) = (rr / 2, bb / 2);
+        let (nr, nb) = (red.count_ones(), blue.count_ones());
+        counts.three = [choose2(nb) - bb, bb, nr * nb - rb, rb, choose2(nr) - rr, rr];
+
+        let others = all & !(1 << v);
+        let mut ma = others;
+        while ma != 0 {
+            let a = ma.trailing_zeros() as usize;
+            ma &= ma - 1;
+            let code_a = (red >> a) as usize & 1;
+            let mut mb = ma;
+            while mb != 0 {
+                let b = mb.trailing_zeros() as usize;
+                mb &= mb - 1;
+                let code_b =
+                    code_a | ((red >> b) as usize & 1) << 1 | ((rows[a] >> b) as usize & 1) << 3;
+                let mut mc = mb;
+                while mc != 0 {
+                    let c = mc.trailing_zeros() as usize;
+                    mc &= mc - 1;
+                    let code = code_b
+                        | ((red >> c) as usize & 1) << 2
+                        | ((rows[a] >> c) as usize & 1) << 4
+                        | ((rows[b] >> c) as usize & 1) << 5;
+                    counts.four[table[code] as usize] += 1;
+                }
+            }
+        }
+        out.push(counts);
+    }
+    out
+}
+
+fn red_matrix<G: GraphLike + ?Sized>(g: &G) -> Vec<Vec<i128>> {
+    let n = g.num_verts();
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| (i != j && g.get_edge(i, j)) as i128)
+                .collect()
+        })
+        .collect()
+}
+
+/// Characteristic polynomial det(xI - A) of the red adjacency matrix, entry i is
+/// the coefficient of x^i
+///
+/// Uses the Faddeev-LeVerrier recurrence in integers, every division in it is exact.
+/// The intermediate matrices are coefficients of adj(xI - A), so they stay about as
+/// small as the result and fit comfortably in i128 for the graph sizes we handle
+pub fn characteristic_polynomial<G: GraphLike + ?Sized>(g: &G) -> Vec<i128> {
+    let n = g.num_verts();
+    let a = red_matrix(g);
+    let mut coeffs = vec![0i128; n + 1];
+    coeffs[n] = 1;
+    //m holds M_k, am holds A * M_k
+    let mut m = vec![vec![0i128; n]; n];
+    let mut am = vec![vec![0i128; n]; n];
+    for k in 1..=n {
+        for i in 0..n {
+            m[i].copy_from_slice(&am[i]);
+            m[i][i] += coeffs[n - k + 1];
+        }
+        for i in 0..n {
+            for j in 0..n {
+                am[i][j] = (0..n).filter(|&l| a[i][l] != 0).map(|l| m[l][j]).sum();
+            }
+        }
+        let trace: i128 = (0..n).map(|i| am[i][i]).sum();
+        debug_assert_eq!(trace % k as i128, 0);
+        coeffs[n - k] = -trace / k as i128;
+    }
+    coeffs
+}
+
+/// Closed red walks at each vertex, entry `[v][k - 1]` counts walks of length k
+/// that start and end at v, the diagonal of A^k
+///
+/// Counted mod 2^128, so exact as long as (n - 1)^max_len fits in a u128. Past
+/// that they wrap, which keeps them invariant under isomorphism and good enough
+/// for a label
+pub fn closed_walks<G: GraphLike + ?Sized>(g: &G, max_len: usize) -> Vec<Vec<u128>> {
+    let n = g.num_verts();
+    let rows = red_rows(g);
+    let mut out = vec![Vec::with_capacity(max_len); n];
+    //power[i][j] is the number of walks of the current length from i to j
+    let mut power: Vec<Vec<u128>> = (0..n)
+        .map(|i| (0..n).map(|j| (rows[i] >> j & 1) as u128).collect())
+        .collect();
+    let mut next = vec![vec![0u128; n]; n];
+    for _ in 0..max_len {
+        for v in 0..n {
+            out[v].push(power[v][v]);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                let mut m = rows[j];
+                let mut walks = 0;
+                while m != 0 {
+                    let l = m.trailing_zeros() as usize;
+                    m &= m - 1;
+                    walks = power[i][l].wrapping_add(walks);
+                }
+                next[i][j] = walks;
+            }
+        }
+        std::mem::swap(&mut power, &mut next);
+    }
+    out
+}
+
+/// Groups of graphs whose red graphs share a characteristic polynomial, only groups
+/// with at least two members are returned. Same spectrum, possibly isomorphic: the
+/// graphs are not checked against each other, so copies of one graph end up in
+/// the same group unless the input has none, like the classes of a tier
+pub fn cospectral_classes<'a, G, I>(graphs: I) -> Vec<Vec<&'a G>>
+where
+    G: GraphLike + 'a,
+    I: IntoIterator<Item = &'a G>,
+{
+    let mut by_poly: HashMap<Vec<i128>, Vec<&'a G>> = HashMap::new();
+    for g in graphs {
+        by_poly
+            .entry(characteristic_polynomial(g))
+            .or_default()
+            .push(g);
+    }
+    by_poly.into_values().filter(|v| v.len() > 1).collect()
+}
+
+/// The number of permutations of the vertices that keep every edge color, |Aut|
+///
+/// Enumerates them by backtracking, mapping vertices in order to unused vertices
+/// of the same red degree whose edges to the vertices mapped so far agree. Fine
+/// for the sizes tiers get to, but it visits every automorphism
+pub fn automorphism_count<G: GraphLike + ?Sized>(g: &G) -> u64 {
+    let n = g.num_verts();
+    let degrees: Vec<usize> = (0..n)
+        .map(|v| (0..n).filter(|&u| u != v && g.get_edge(u, v)).count())
+        .collect();
+    let mut image = Vec::with_capacity(n);
+    let mut used = vec![false; n];
+    count_extensions(g, &degrees, &mut image, &mut used)
+}
+
+fn count_extensions<G: GraphLike + ?Sized>(
+    g: &G,
+    degrees: &[usize],
+    image: &mut Vec<usize>,
+    used: &mut [bool],
+) -> u64 {
+    let v = image.len();
+    if v == g.num_verts() {
+        return 1;
+    }
+    let mut count = 0;
+    for w in 0..g.num_verts() {
+        if used[w] || degrees[w] != degrees[v] {
+            continue;
+        }
+        if (0..v).any(|u| g.edge_color(u, v) != g.edge_color(image[u], w)) {
+            continue;
+        }
+        used[w] = true;
+        image.push(w);
+        count += count_extensions(g, degrees, image, used);
+        image.pop();
+        used[w] = false;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+    use crate::graph_like::GraphMut;
+
+    #[test]
+    fn five_cycle() {
+        let pentagon = Graph::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]).unwrap();
+        let census = graphlet_census(&pentagon);
+        for c in census.iter() {
+            assert_eq!(c.three, [0, 1, 2, 2, 1, 0]);
+            assert_eq!(c.four.iter().sum::<u32>(), 4);
+            assert_eq!(c, &census[0]);
+        }
+    }
+
+    #[test]
+    fn automorphisms() {
+        let pentagon = Graph::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]).unwrap();
+        assert_eq!(automorphism_count(&pentagon), 10);
+        assert_eq!(automorphism_count(&Graph::new(4)), 24);
+        assert_eq!(automorphism_count(&Graph::new(0)), 1);
+        // a path on three vertices and a triangle with a pendant vertex
+        let path = Graph::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+        assert_eq!(automorphism_count(&path), 2);
+        let pendant = Graph::from_edges(4, &[(0, 1), (0, 2), (1, 2), (2, 3)]).unwrap();
+        assert_eq!(automorphism_count(&pendant), 2);
+        let paley: Graph = crate::constructions::paley(13);
+        assert_eq!(automorphism_count(&paley), 78);
+    }
+
+    #[test]
+    fn totals() {
+        let edges = [
+            (0, 1), (0, 2), (1, 2), (2, 3), (1, 4), (3, 4),
+            (1, 5), (2, 5), (4, 5), (0, 6), (5, 6),
+        ];
+        let g = Graph::from_edges(7, &edges).unwrap();
+        let n = g.num_verts() as u32;
+        let census = graphlet_census(&g);
+        let three: u32 = census.iter().map(|c| c.three.iter().sum::<u32>()).sum();
+        let four: u32 = census.iter().map(|c| c.four.iter().sum::<u32>()).sum();
+        assert_eq!(three, n * (n - 1) * (n - 2) / 2);
+        assert_eq!(four, n * (n - 1) * (n - 2) * (n - 3) / 6);
+
+        // every red K4 has each of its vertices in the last orbit
+        let red_k4s: u32 = g.label_k4s(true).iter().sum();
+        let last: u32 = census.iter().map(|c| c.four[FOUR_ORBITS - 1]).sum();
+        assert_eq!(red_k4s, last);
+    }
+
+    #[test]
+    fn characteristic_polynomials() {
+        let triangle = Graph::from_edges(3, &[(0, 1), (0, 2), (1, 2)]).unwrap();
+        assert_eq!(characteristic_polynomial(&triangle), vec![-2, -3, 0, 1]);
+        let square = Graph::from_edges(4, &[(0, 1), (1, 2), (0, 3), (2, 3)]).unwrap();
+        assert_eq!(characteristic_polynomial(&square), vec![0, 0, -4, 0, 1]);
+        assert_eq!(characteristic_polynomial(&Graph::new(3)), vec![0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn cospectral() {
+        // the star K1,4 and a square plus an isolated vertex, both x^5 - 4x^3
+        let star = Graph::from_edges(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]).unwrap();
+        let square = Graph::from_edges(5, &[(0, 1), (1, 2), (0, 3), (2, 3)]).unwrap();
+        let path = Graph::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4)]).unwrap();
+        assert_eq!(characteristic_polynomial(&star), vec![0, 0, 0, -4, 0, 1]);
+        let classes = cospectral_classes(vec![&star, &path, &square]);
+        assert_eq!(classes.len(), 1);
+        assert_eq!(classes[0].len(), 2);
+    }
+
+    #[test]
+    fn walks() {
+        let edges = [(0, 1), (0, 2), (1, 2), (2, 3), (1, 4), (3, 4), (1, 5), (2, 5), (4, 5)];
+        let g = Graph::from_edges(6, &edges).unwrap();
+        let walks = closed_walks(&g, 4);
+        let degrees = g.label_degrees(true);
+        let triangles = g.label_k3s(true);
+        for v in 0..g.num_verts() {
+            assert_eq!(walks[v][0], 0);
+            assert_eq!(walks[v][1], degrees[v] as u128);
+            assert_eq!(walks[v][2], 2 * triangles[v] as u128);
+        }
+        // the trace of A^k is the sum of the k-th powers of the eigenvalues, by
+        // Newton's identities tr(A^2) = -2 c_(n-2) when there are no loops
+        let poly = characteristic_polynomial(&g);
+        let n = g.num_verts();
+        let tr2: u128 = walks.iter().map(|w| w[1]).sum();
+        assert_eq!(tr2 as i128, -2 * poly[n - 2]);
+
+        // a closed walk of length k in K_n comes back in ((n-1)^k + (n-1)(-1)^k) / n ways,
+        // which stops fitting a u128 at k = 27 for n = 30
+        let complete = Graph::new(30).complement();
+        let walks = closed_walks(&complete, 40);
+        for k in 1..=26 {
+            let power = 29u128.pow(k as u32);
+            let expected = if k % 2 == 0 { power + 29 } else { power - 29 } / 30;
+            assert_eq!(walks[0][k - 1], expected);
+        }
+        assert!(walks.iter().all(|w| w == &walks[0]));
+    }
+
+    #[test]
+    fn orbit_table() {
+        let table = four_orbit_table();
+        assert_eq!(table[0], 0);
+        assert_eq!(table[63] as usize, FOUR_ORBITS - 1);
+        // v joined to exactly one of the other three, no other red edges
+        assert_eq!(table[0b1], table[0b10]);
+        assert_eq!(table[0b1], table[0b100]);
+        // v isolated next to a single edge
+        assert_eq!(table[0b1000], table[0b10_0000]);
+        assert_ne!(table[0b1], table[0b1000]);
+    }
+}
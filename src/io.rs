@@ -0,0 +1,456 @@
+// Tier files, in one of three formats
+//
+// Base64: one chunk per line, the graphs of a chunk separated by ';', each the
+// base64 of its edge bits. The number of vertices is not stored
+// Graph6: one graph per line in the format of nauty's geng, red edges are the
+// edges. It stores edges in the same order as the base64 encoding
+// Labeled: one graph per line, its base64 then a space and the labels of its
+// vertices separated by ','. Reading it back needs no labeler, which matters
+// for labelers like `Deck` that depend on the previous tier
+
+use crate::error::Error;
+use crate::graph::Graph;
+use crate::graph_like::{GraphLike, GraphMut};
+use crate::labeler::Labeler;
+use crate::sink::ChunkSink;
+use crate::structures::{Chunk, Tier};
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Base64,
+    Graph6,
+    Labeled,
+}
+
+impl Format {
+    /// The file extension tiers of this format are written with
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Base64 => "txt",
+            Format::Graph6 => "g6",
+            Format::Labeled => "lbl",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "b64" | "base64" => Ok(Format::Base64),
+            "g6" | "graph6" => Ok(Format::Graph6),
+            "lbl" | "labeled" => Ok(Format::Labeled),
+            _ => Err(Error::InvalidSpec(format!(
+                "unknown format `{}`, expected b64, g6 or lbl",
+                s
+            ))),
+        }
+    }
+}
+
+/// The graph6 string of the red graph of g
+pub fn to_graph6<G: GraphLike + ?Sized>(g: &G) -> String {
+    let n = g.num_verts();
+    let mut out = String::new();
+    if n < 63 {
+        out.push((n as u8 + 63) as char);
+    } else {
+        out.push('~');
+        for shift in [12, 6, 0].iter() {
+            out.push(((n >> shift & 63) as u8 + 63) as char);
+        }
+    }
+    let mut byte = 0;
+    let mut bits = 0;
+    for m in 0..n {
+        for k in 0..m {
+            byte = byte << 1 | g.get_edge(k, m) as u8;
+            bits += 1;
+            if bits == 6 {
+                out.push((byte + 63) as char);
+                byte = 0;
+                bits = 0;
+            }
+        }
+    }
+    if bits > 0 {
+        out.push(((byte << (6 - bits)) + 63) as char);
+    }
+    out
+}
+
+/// Parses a graph6 string, for graphs of fewer than 2^18 vertices
+pub fn from_graph6<G: GraphMut>(s: &str) -> Result<G, Error> {
+    let bytes = s.as_bytes();
+    if bytes.iter().any(|&b| !(63..=126).contains(&b)) {
+        return Err(Error::InvalidGraph6(s.to_string()));
+    }
+    let (n, rest) = match bytes.first() {
+        None => return Err(Error::InvalidGraph6(s.to_string())),
+        Some(b'~') if bytes.len() >= 4 && bytes[1] != b'~' => (
+            bytes[1..4]
+                .iter()
+                .fold(0, |n, &b| n << 6 | (b - 63) as usize),
+            &bytes[4..],
+        ),
+        Some(b'~') => return Err(Error::InvalidGraph6(s.to_string())),
+        Some(&b) => ((b - 63) as usize, &bytes[1..]),
+    };
+    if n > G::MAX_VERTS {
+        return Err(Error::TooManyVertices {
+            num_verts: n,
+            max: G::MAX_VERTS,
+        });
+    }
+    let num_edges = n * n.saturating_sub(1) / 2;
+    let expected = num_edges.div_ceil(6);
+    if rest.len() != expected {
+        return Err(Error::WrongLength {
+            len: rest.len(),
+            expected,
+            num_verts: n,
+        });
+    }
+    if !num_edges.is_multiple_of(6) && (rest[expected - 1] - 63) & (63 >> (num_edges % 6)) != 0 {
+        return Err(Error::PaddingBits { num_verts: n });
+    }
+    let mut g = G::empty(n);
+    let mut i = 0;
+    for m in 0..n {
+        for k in 0..m {
+            if (rest[i / 6] - 63) >> (5 - i % 6) & 1 != 0 {
+                g.set_edge(k, m, true);
+            }
+            i += 1;
+        }
+    }
+    Ok(g)
+}
+
+pub fn write_tier<T, W>(tier: &Tier<T>, mut writer: W, format: Format) -> Result<(), Error>
+where
+    T: Hash + Debug + Display + Eq + Clone + Ord + Send + Sync,
+    W: Write,
+{
+    let mut buf = String::new();
+    for chunk in tier.map.values() {
+        write_chunk(chunk, &mut buf, &mut writer, format)?;
+    }
+    Ok(writer.flush()?)
+}
+
+// the lines of one chunk, buf is scratch space
+fn write_chunk<T, W>(chunk: &Chunk<T>, buf: &mut String, writer: &mut W, format: Format) -> Result<(), Error>
+where
+    T: Hash + Eq + Clone + Display,
+    W: Write,
+{
+    buf.clear();
+    for (i, graph) in chunk.checked.iter().enumerate() {
+        match format {
+            Format::Base64 => {
+                if i > 0 {
+                    buf.push(';');
+                }
+                graph.inner.to_string_append(buf);
+            }
+            Format::Graph6 => {
+                buf.push_str(&to_graph6(&graph.inner));
+                buf.push('\n');
+            }
+            Format::Labeled => {
+                graph.inner.to_string_append(buf);
+                for (v, label) in chunk.vertex_labels(graph).iter().enumerate() {
+                    buf.push(if v == 0 { ' ' } else { ',' });
+                    buf.push_str(&label.to_string());
+                }
+                buf.push('\n');
+            }
+        }
+    }
+    if format == Format::Base64 && !chunk.checked.is_empty() {
+        buf.push('\n');
+    }
+    Ok(writer.write_all(buf.as_bytes())?)
+}
+
+/// A `ChunkSink` writing every chunk it gets in the format of `write_tier`,
+/// e.g. for a tier too large to keep. The first error stops the writing and
+/// comes back from `finish`
+#[derive(Debug)]
+pub struct ChunkWriter<W> {
+    writer: W,
+    format: Format,
+    buf: String,
+    error: Option<Error>,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    pub fn new(writer: W, format: Format) -> Self {
+        ChunkWriter {
+            writer,
+            format,
+            buf: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => {
+                self.writer.flush()?;
+                Ok(self.writer)
+            }
+        }
+    }
+}
+
+impl<T, W> ChunkSink<T, Graph> for ChunkWriter<W>
+where
+    T: Hash + Eq + Clone + Display,
+    W: Write,
+{
+    fn chunk(&mut self, chunk: Chunk<T>) {
+        if self.error.is_none() {
+            let written = write_chunk(&chunk, &mut self.buf, &mut self.writer, self.format);
+            self.error = written.err();
+        }
+    }
+}
+
+/// Reads a tier written by `write_tier` and chunks it with labeler, the chunks in
+/// the file are not kept. num_verts is only needed for base64, the others store
+/// it. Labeled files keep their labels and never call labeler. Errors in the
+/// graphs come as `Error::AtLine`
+pub fn read_tier<T, L, R>(
+    reader: R,
+    format: Format,
+    num_verts: usize,
+    labeler: &L,
+) -> Result<Tier<T>, Error>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync + FromStr,
+    L: Labeler<T> + ?Sized,
+    R: BufRead,
+{
+    let mut out = Tier::default();
+    for (i, line) in reader.lines().enumerate() {
+        read_line(&mut out, &line?, format, num_verts, labeler)
+            .map_err(|e| Error::AtLine(i + 1, Box::new(e)))?;
+    }
+    Ok(out)
+}
+
+fn read_line<T, L>(
+    out: &mut Tier<T>,
+    line: &str,
+    format: Format,
+    num_verts: usize,
+    labeler: &L,
+) -> Result<(), Error>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync + FromStr,
+    L: Labeler<T> + ?Sized,
+{
+    match format {
+        Format::Base64 => {
+            for graph_b64 in line.split(';').map(str::trim) {
+                if !graph_b64.is_empty() {
+                    out.insert_checked(Graph::from_str(graph_b64, num_verts)?, labeler);
+                }
+            }
+        }
+        Format::Graph6 => {
+            let line = line.trim();
+            if !line.is_empty() {
+                out.insert_checked(from_graph6(line)?, labeler);
+            }
+        }
+        Format::Labeled => {
+            // a graph on one vertex has no edge bits, its line starts with the
+            // separator so only the end is trimmed
+            let line = line.trim_end();
+            if line.is_empty() {
+                return Ok(());
+            }
+            let (graph_b64, labels) = line.split_once(' ').unwrap_or((line, ""));
+            let labels: Vec<T> = labels
+                .split(',')
+                .map(|l| l.trim().parse().map_err(|_| Error::InvalidLabel(l.to_string())))
+                .collect::<Result<_, _>>()?;
+            out.insert_labeled(Graph::from_str(graph_b64, labels.len())?, &labels);
+        }
+    }
+    Ok(())
+}
+
+/// `read_tier` on a file, the format is picked from the extension, anything but
+/// .g6 and .lbl is read as base64
+pub fn read_tier_file<T, L>(path: &Path, num_verts: usize, labeler: &L) -> Result<Tier<T>, Error>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync + FromStr,
+    L: Labeler<T> + ?Sized,
+{
+    let format = match path.extension() {
+        Some(ext) if ext == "g6" => Format::Graph6,
+        Some(ext) if ext == "lbl" => Format::Labeled,
+        _ => Format::Base64,
+    };
+    let file = File::open(path)?;
+    read_tier(BufReader::new(file), format, num_verts, labeler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::Pipeline;
+    use crate::test_util::tier_up_to;
+
+    #[test]
+    fn round_trip() {
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(5, &labeler);
+        let chunks = tier.count_chunks();
+        let formats = [(Format::Base64, chunks), (Format::Graph6, 24), (Format::Labeled, 24)];
+        for &(format, lines) in formats.iter() {
+            let mut file = vec![];
+            write_tier(&tier, &mut file, format).unwrap();
+            assert_eq!(file.iter().filter(|&&b| b == b'\n').count(), lines);
+
+            let read: Tier<u32> = read_tier(&file[..], format, 5, &labeler).unwrap();
+            assert_eq!(read.count_graphs(), tier.count_graphs());
+            assert_eq!(read.count_chunks(), tier.count_chunks());
+            assert_eq!(read.diff(&tier, &labeler).count_graphs().0, 0);
+        }
+
+        assert!(read_tier::<u32, _, _>(&b"AA;?!\n"[..], Format::Base64, 3, &labeler).is_err());
+        assert!(read_tier::<u32, _, _>(&b"Bw\nB\n"[..], Format::Graph6, 0, &labeler).is_err());
+        assert!(read_tier::<u32, _, _>(&b"Bw\n"[..], Format::Labeled, 0, &labeler).is_err());
+        assert!(read_tier::<u32, _, _>(&b"Bw 1,x,3\n"[..], Format::Labeled, 0, &labeler).is_err());
+    }
+
+    #[test]
+    fn single_vertex() {
+        let labeler = Pipeline::standard();
+        let tier = Tier::from_graph(Graph::new(1), &labeler);
+        let mut file = vec![];
+        write_tier(&tier, &mut file, Format::Labeled).unwrap();
+        assert!(file.starts_with(b" "));
+        let read: Tier<u32> = read_tier(&file[..], Format::Labeled, 0, &labeler).unwrap();
+        assert_eq!(read.count_graphs().0, 1);
+        assert_eq!(read.diff(&tier, &labeler).count_graphs().0, 0);
+    }
+
+    #[test]
+    fn chunk_writer() {
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(4, &labeler);
+        let options = crate::structures::GenerateOptions::default();
+        let mut sink = ChunkWriter::new(vec![], Format::Graph6);
+        tier.generate_next_size_streamed(&labeler, &options, &crate::observer::Silent, 2, &mut sink);
+        let file = sink.finish().unwrap();
+        let read: Tier<u32> = read_tier(&file[..], Format::Graph6, 0, &labeler).unwrap();
+        assert_eq!(read.count_graphs().0, 24);
+        assert_eq!(read.diff(&tier.generate_next_size(&labeler), &labeler).count_graphs().0, 0);
+    }
+
+    #[test]
+    fn stored_labels() {
+        // a labeler that can't be run again, the stored labels have to be used
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(4, &labeler);
+        let mut file = vec![];
+        write_tier(&tier, &mut file, Format::Labeled).unwrap();
+        let panics = |_: &Graph| -> Vec<u32> { panic!("labels are stored") };
+        let read: Tier<u32> = read_tier(&file[..], Format::Labeled, 0, &panics).unwrap();
+        let mut chunks: Vec<_> = read.map.keys().collect();
+        let mut expected: Vec<_> = tier.map.keys().collect();
+        chunks.sort_by_key(|c| format!("{:?}", c));
+        expected.sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(chunks, expected);
+        assert_eq!(read.diff(&tier, &labeler).count_graphs().0, 0);
+    }
+
+    #[test]
+    fn graph6() {
+        // the example from the format description
+        let path = Graph::from_edges(5, &[(0, 2), (0, 4), (1, 3), (3, 4)]).unwrap();
+        assert_eq!(to_graph6(&path), "DQc");
+        let g: Graph = from_graph6("DQc").unwrap();
+        assert_eq!(g.to_edges(), path.to_edges());
+        assert_eq!(to_graph6(&Graph::new(1)), "@");
+        assert_eq!(to_graph6(&Graph::new(0)), "?");
+
+        assert!(from_graph6::<Graph>("DQ").is_err());
+        assert!(from_graph6::<Graph>("D Qc").is_err());
+        assert!(from_graph6::<Graph>("").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let labeler = Pipeline::standard();
+        let read = |file: &[u8], format, n| read_tier::<u32, _, _>(file, format, n, &labeler).unwrap_err();
+        let at = |line, e| Error::AtLine(line, Box::new(e));
+
+        // five vertices have ten edges, two bytes of base64
+        let truncated = at(2, Error::WrongLength { len: 1, expected: 2, num_verts: 5 });
+        assert_eq!(read(b"/8A\n/w\n", Format::Base64, 5), truncated);
+        // a tier of another size
+        assert_eq!(
+            read(b"/8A\n", Format::Base64, 7),
+            at(1, Error::WrongLength { len: 2, expected: 3, num_verts: 7 })
+        );
+        assert_eq!(
+            read(b"/8E\n", Format::Base64, 5),
+            at(1, Error::PaddingBits { num_verts: 5 })
+        );
+        assert_eq!(
+            read(b"@\nDQd\n", Format::Graph6, 0),
+            at(2, Error::PaddingBits { num_verts: 5 })
+        );
+        assert_eq!(
+            read(b"/8A 1,x,3,4,5\n", Format::Labeled, 0),
+            at(1, Error::InvalidLabel("x".to_string()))
+        );
+        // without labels the graph is read as one on a single vertex
+        assert_eq!(
+            read(b"/8A\n", Format::Labeled, 0),
+            at(1, Error::InvalidLabel(String::new()))
+        );
+        assert_eq!(from_graph6::<Graph>("D Qc").unwrap_err(), Error::InvalidGraph6("D Qc".to_string()));
+        assert!(matches!("nope".parse::<Format>(), Err(Error::InvalidSpec(_))));
+
+        let missing = read_tier_file::<u32, _>(Path::new("no/such/tier.txt"), 5, &labeler);
+        assert!(matches!(missing, Err(Error::Io { kind: std::io::ErrorKind::NotFound, .. })));
+    }
+
+    // the bitboard backend stops at 64 vertices
+    #[test]
+    fn graph6_long_size() {
+        let max = <Graph as GraphMut>::MAX_VERTS;
+        if max < 70 {
+            // the empty graph on 70 vertices, 2415 edge bits in 403 bytes
+            let s = format!("~?@E{}", "?".repeat(403));
+            assert_eq!(
+                from_graph6::<Graph>(&s).unwrap_err(),
+                Error::TooManyVertices {
+                    num_verts: 70,
+                    max
+                }
+            );
+            return;
+        }
+        let big: Graph = crate::constructions::circulant(70, &[1, 7]).unwrap();
+        let s = to_graph6(&big);
+        assert!(s.starts_with("~?@E"));
+        assert_eq!(from_graph6::<Graph>(&s).unwrap().to_edges(), big.to_edges());
+    }
+}
@@ -0,0 +1,646 @@
+// Vertex labelers, these decide which chunk of a tier a graph lands in
+
+use crate::error::Error;
+use crate::graph::Graph;
+use crate::graph_like::{Extendable, GraphLike, GraphMut};
+use crate::invariants;
+use crate::structures::Tier;
+use crate::util;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Assigns every vertex of a graph a label that is invariant under isomorphism,
+/// the sorted labels of a graph are its chunk labeling
+pub trait Labeler<T, G: ?Sized = Graph>: Sync {
+    fn label(&self, g: &G) -> Vec<T>;
+}
+
+impl<T, G: ?Sized, F: Fn(&G) -> Vec<T> + Sync> Labeler<T, G> for F {
+    fn label(&self, g: &G) -> Vec<T> {
+        self(g)
+    }
+}
+
+/// One step of a `Pipeline`, refines the running labels in place
+///
+/// Generic over the graph so every backend gets its own copy of the stages,
+/// with the edge lookups in their inner loops resolved at compile time
+pub trait Stage<G: ?Sized = Graph>: Debug + Send + Sync {
+    fn refine(&self, g: &G, labels: &mut [u32]);
+}
+
+// folds a per vertex invariant into the running labels
+fn mix(labels: &mut [u32], values: &[u32]) {
+    for (l, v) in labels.iter_mut().zip(values) {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u32(*l);
+        hasher.write_u32(*v);
+        *l = hasher.finish() as u32;
+    }
+}
+
+/// Which edges an invariant is computed over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Blue,
+    /// Computed separately in each color, then combined
+    Both,
+}
+
+impl Color {
+    fn cols(self) -> &'static [bool] {
+        match self {
+            Color::Red => &[true],
+            Color::Blue => &[false],
+            Color::Both => &[true, false],
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "red" => Ok(Color::Red),
+            "blue" => Ok(Color::Blue),
+            "both" => Ok(Color::Both),
+            _ => Err(Error::InvalidSpec(format!("unknown color `{}`", s))),
+        }
+    }
+}
+
+/// Number of edges at each vertex
+#[derive(Clone, Copy, Debug)]
+pub struct Degree(pub Color);
+
+impl<G: GraphLike + ?Sized> Stage<G> for Degree {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        for col in self.0.cols() {
+            mix(labels, &g.label_degrees(*col));
+        }
+    }
+}
+
+/// Histogram of the shortest cycles through each pair of edges at a vertex
+#[derive(Clone, Copy, Debug)]
+pub struct Polygons(pub Color);
+
+impl<G: GraphLike + ?Sized> Stage<G> for Polygons {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        for col in self.0.cols() {
+            mix(labels, &g.label_polygons(*col));
+        }
+    }
+}
+
+/// Number of red and of blue k-cliques each vertex is a part of
+#[derive(Clone, Copy, Debug)]
+pub struct Cliques(usize);
+
+impl Cliques {
+    /// There are clique counters for k in 3..=5
+    pub fn new(k: usize) -> Result<Self, Error> {
+        match k {
+            3..=5 => Ok(Cliques(k)),
+            _ => Err(Error::InvalidSpec(format!("no clique counter for k = {}", k))),
+        }
+    }
+}
+
+impl<G: GraphLike + ?Sized> Stage<G> for Cliques {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let (red, blue) = match self.0 {
+            3 => (g.label_k3s(true), g.label_k3s(false)),
+            4 => (g.label_k4s(true), g.label_k4s(false)),
+            _ => (g.label_k5s(true), g.label_k5s(false)),
+        };
+        mix(labels, &red);
+        mix(labels, &blue);
+    }
+}
+
+/// Rounds of `Graph::convolute`, each hashes in the labels seen at every BFS distance
+///
+/// With `Color::Both` every round grows the red and the blue trees from the same
+/// labels and combines the two results
+#[derive(Clone, Copy, Debug)]
+pub struct Convolute(pub usize, pub Color);
+
+thread_local! {
+    // the BFS trees `Graph::convolute` collects, one set per worker so labeling
+    // a child does not allocate them again
+    static TREES: RefCell<Vec<Vec<(u32, u32)>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl<G: GraphLike + ?Sized> Stage<G> for Convolute {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let n = g.num_verts();
+        TREES.with(|trees| {
+            let buf = &mut *trees.borrow_mut();
+            if buf.len() < n {
+                buf.resize(n, Vec::with_capacity(n));
+            }
+            for _ in 0..self.0 {
+                match self.1 {
+                    Color::Red => g.convolute(labels, buf, true),
+                    Color::Blue => g.convolute(labels, buf, false),
+                    Color::Both => {
+                        let mut blue = labels.to_vec();
+                        g.convolute(labels, buf, true);
+                        g.convolute(&mut blue, buf, false);
+                        mix(labels, &blue);
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Per vertex counts of every induced 3 and 4 vertex subgraph, by orbit,
+/// see `invariants::graphlet_census`
+#[derive(Clone, Copy, Debug)]
+pub struct Graphlets;
+
+impl<G: GraphLike + ?Sized> Stage<G> for Graphlets {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let census: Vec<u32> = invariants::graphlet_census(g)
+            .iter()
+            .map(|c| {
+                let mut hasher = DefaultHasher::new();
+                c.hash(&mut hasher);
+                hasher.finish() as u32
+            })
+            .collect();
+        mix(labels, &census);
+    }
+}
+
+/// Closed red walks of every length up to the given one at each vertex, the
+/// diagonal of the powers of the adjacency matrix. A length of 0 means n
+#[derive(Clone, Copy, Debug)]
+pub struct Walks(pub usize);
+
+impl<G: GraphLike + ?Sized> Stage<G> for Walks {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let len = if self.0 == 0 { g.num_verts() } else { self.0 };
+        let walks: Vec<u32> = invariants::closed_walks(g, len)
+            .iter()
+            .map(|w| {
+                let mut hasher = DefaultHasher::new();
+                w.hash(&mut hasher);
+                hasher.finish() as u32
+            })
+            .collect();
+        mix(labels, &walks);
+    }
+}
+
+fn count_classes(labels: &[u32]) -> usize {
+    let mut sorted = labels.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    sorted.len()
+}
+
+/// 1-dimensional Weisfeiler-Leman color refinement, run until the partition is stable
+///
+/// Every pair of vertices is joined in some color, so the signature of a vertex
+/// is the multiset of (edge color, label) over all other vertices
+#[derive(Clone, Copy, Debug)]
+pub struct Wl1;
+
+impl<G: GraphLike + ?Sized> Stage<G> for Wl1 {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let n = g.num_verts();
+        let mut classes = count_classes(labels);
+        let mut sig = Vec::with_capacity(n);
+        let mut next = vec![0; n];
+        //a stable partition is reached after at most n rounds
+        for _ in 0..n {
+            for v in 0..n {
+                sig.clear();
+                for (u, l) in labels.iter().enumerate() {
+                    if u != v {
+                        sig.push((g.edge_color(u, v), *l));
+                    }
+                }
+                sig.sort();
+                let mut hasher = DefaultHasher::new();
+                hasher.write_u32(labels[v]);
+                sig.hash(&mut hasher);
+                next[v] = hasher.finish() as u32;
+            }
+            labels.copy_from_slice(&next);
+            let next_classes = count_classes(labels);
+            if next_classes == classes {
+                break;
+            }
+            classes = next_classes;
+        }
+    }
+}
+
+/// 2-dimensional Weisfeiler-Leman refinement, colors ordered pairs of vertices
+///
+/// A pair (u, v) starts out colored by its edge and the labels of u and v, then
+/// repeatedly takes in the multiset of (color(u, w), color(w, v)) over all w.
+/// Costs O(n^3 log n) per round, meant for the hardest chunks
+#[derive(Clone, Copy, Debug)]
+pub struct Wl2;
+
+impl<G: GraphLike + ?Sized> Stage<G> for Wl2 {
+    fn refine(&self, g: &G, labels: &mut [u32]) {
+        let n = g.num_verts();
+        let mut pairs = vec![0u32; n * n];
+        for u in 0..n {
+            for v in 0..n {
+                let mut hasher = DefaultHasher::new();
+                hasher.write_u8(if u == v { u8::MAX } else { g.edge_color(u, v) });
+                hasher.write_u32(labels[u]);
+                hasher.write_u32(labels[v]);
+                pairs[u * n + v] = hasher.finish() as u32;
+            }
+        }
+        let mut classes = count_classes(&pairs);
+        let mut sig = Vec::with_capacity(n);
+        let mut next = vec![0; n * n];
+        for _ in 0..n * n {
+            for u in 0..n {
+                for v in 0..n {
+                    sig.clear();
+                    for w in 0..n {
+                        sig.push((pairs[u * n + w], pairs[w * n + v]));
+                    }
+                    sig.sort();
+                    let mut hasher = DefaultHasher::new();
+                    hasher.write_u32(pairs[u * n + v]);
+                    sig.hash(&mut hasher);
+                    next[u * n + v] = hasher.finish() as u32;
+                }
+            }
+            std::mem::swap(&mut pairs, &mut next);
+            let next_classes = count_classes(&pairs);
+            if next_classes == classes {
+                break;
+            }
+            classes = next_classes;
+        }
+        let mut row = Vec::with_capacity(n);
+        for v in 0..n {
+            row.clear();
+            row.extend_from_slice(&pairs[v * n..(v + 1) * n]);
+            row.sort();
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u32(labels[v]);
+            hasher.write_u32(pairs[v * n + v]);
+            row.hash(&mut hasher);
+            labels[v] = hasher.finish() as u32;
+        }
+    }
+}
+
+/// A labeler built out of stages that run in order, starting from all labels equal
+///
+/// Parses from a comma separated list of stages, e.g. `degree,polygons:both,k3,conv:2`,
+/// where degree, polygons and conv take an optional color (red by default) and conv
+/// an optional number of rounds. `wl1` and `wl2` run color refinement to a stable partition,
+/// `graphlets` counts induced subgraphs on 3 and 4 vertices and `walks` takes an optional
+/// maximum length
+pub struct Pipeline<G: ?Sized = Graph> {
+    stages: Vec<Box<dyn Stage<G>>>,
+}
+
+impl<G: ?Sized> Default for Pipeline<G> {
+    fn default() -> Self {
+        Pipeline { stages: Vec::new() }
+    }
+}
+
+impl<G: ?Sized> Debug for Pipeline<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Pipeline").field("stages", &self.stages).finish()
+    }
+}
+
+impl<G: GraphLike + ?Sized> Pipeline<G> {
+    /// The labeling the generator has always used: degree, polygons and one convolution
+    pub fn standard() -> Self {
+        Pipeline::default()
+            .then(Degree(Color::Red))
+            .then(Polygons(Color::Red))
+            .then(Convolute(1, Color::Red))
+    }
+
+    pub fn then<S: Stage<G> + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn stages(&self) -> &[Box<dyn Stage<G>>] {
+        &self.stages
+    }
+}
+
+impl<G: GraphLike + ?Sized> Labeler<u32, G> for Pipeline<G> {
+    fn label(&self, g: &G) -> Vec<u32> {
+        let mut labels = vec![0; g.num_verts()];
+        for stage in self.stages.iter() {
+            stage.refine(g, &mut labels);
+        }
+        labels
+    }
+}
+
+/// Labels with `cheap`, and only when the resulting chunk would be expensive to
+/// clean, that is its complexity exceeds `threshold`, mixes in the labels of `strong`
+///
+/// Whether a graph gets refined only depends on its cheap chunk labeling, so every
+/// graph of a costly chunk is relabeled the same way and the chunk splits into
+/// sub-chunks before any iso checking happens
+#[derive(Debug)]
+pub struct Adaptive<C, S> {
+    pub cheap: C,
+    pub strong: S,
+    pub threshold: u64,
+}
+
+impl<G, C, S> Labeler<u32, G> for Adaptive<C, S>
+where
+    G: ?Sized,
+    C: Labeler<u32, G>,
+    S: Labeler<u32, G>,
+{
+    fn label(&self, g: &G) -> Vec<u32> {
+        let mut labels = self.cheap.label(g);
+        if labels.is_empty() {
+            return labels;
+        }
+        let mut sorted = labels.clone();
+        sorted.sort();
+        if util::compute_complexity(&sorted) > self.threshold {
+            mix(&mut labels, &self.strong.label(g));
+        }
+        labels
+    }
+}
+
+/// Labels each vertex v by the isomorphism class of G - v in the previous tier
+///
+/// `prev` has to be labeled with `labeler`, use `Tier::relabel` to get such a
+/// tier back after generating with this labeler. Vertices whose deleted subgraph
+/// is missing from `prev` all get the same label
+#[derive(Debug)]
+pub struct Deck<'a, T: Hash + Debug + Eq + Send, L: ?Sized, G = Graph> {
+    pub prev: &'a Tier<T, G>,
+    pub labeler: &'a L,
+}
+
+impl<'a, T, L, G> Labeler<u32, G> for Deck<'a, T, L, G>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+    L: Labeler<T, G> + ?Sized,
+    G: GraphMut + Extendable + Clone + Send + Sync,
+{
+    fn label(&self, g: &G) -> Vec<u32> {
+        (0..g.num_verts())
+            .map(|v| {
+                let mut card = g.clone();
+                card.remove_vertex(v);
+                let mut hasher = DefaultHasher::new();
+                self.prev
+                    .find(&card, self.labeler)
+                    .hash(&mut hasher);
+                hasher.finish() as u32
+            })
+            .collect()
+    }
+}
+
+const STAGES: &[&str] = &[
+    "degree",
+    "polygons",
+    "k3",
+    "k4",
+    "k5",
+    "graphlets",
+    "walks",
+    "wl1",
+    "wl2",
+    "conv",
+];
+
+impl<G: GraphLike + ?Sized> FromStr for Pipeline<G> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = Pipeline::default();
+        for token in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let mut parts = token.split(':');
+            let name = parts.next().unwrap();
+            let mut rounds = None;
+            let mut color = None;
+            for part in parts {
+                if let Ok(r) = part.parse::<usize>() {
+                    if rounds.replace(r).is_some() {
+                        return Err(Error::InvalidSpec(format!(
+                            "stage `{}` given two counts",
+                            name
+                        )));
+                    }
+                } else if let Ok(c) = part.parse::<Color>() {
+                    if color.replace(c).is_some() {
+                        return Err(Error::InvalidSpec(format!(
+                            "stage `{}` given two colors",
+                            name
+                        )));
+                    }
+                } else {
+                    return Err(Error::InvalidSpec(format!(
+                        "bad argument `{}` for stage `{}`",
+                        part, name
+                    )));
+                }
+            }
+            let col = color.unwrap_or(Color::Red);
+            out = match (name, rounds, color) {
+                ("degree", None, _) => out.then(Degree(col)),
+                ("polygons", None, _) => out.then(Polygons(col)),
+                ("k3", None, None) => out.then(Cliques::new(3)?),
+                ("k4", None, None) => out.then(Cliques::new(4)?),
+                ("k5", None, None) => out.then(Cliques::new(5)?),
+                ("graphlets", None, None) => out.then(Graphlets),
+                ("walks", len, None) => out.then(Walks(len.unwrap_or(0))),
+                ("wl1", None, None) => out.then(Wl1),
+                ("wl2", None, None) => out.then(Wl2),
+                ("conv", rounds, _) => out.then(Convolute(rounds.unwrap_or(1), col)),
+                _ if STAGES.contains(&name) => {
+                    return Err(Error::InvalidSpec(format!(
+                        "stage `{}` does not take `{}`",
+                        name, token
+                    )))
+                }
+                _ => {
+                    return Err(Error::InvalidSpec(format!(
+                        "unknown labeler stage `{}`",
+                        name
+                    )))
+                }
+            };
+        }
+        if out.stages.is_empty() {
+            return Err(Error::InvalidSpec("labeler needs at least one stage".to_string()));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let p: Pipeline = "degree, polygons:blue,k4,conv:3:both".parse().unwrap();
+        assert_eq!(p.stages().len(), 4);
+        assert!("degree,conv".parse::<Pipeline>().is_ok());
+        assert!("graphlets,wl1,walks,walks:4".parse::<Pipeline>().is_ok());
+        assert!("conv:both:2".parse::<Pipeline>().is_ok());
+        assert!("degree,hexagons".parse::<Pipeline>().is_err());
+        assert!("degree:2".parse::<Pipeline>().is_err());
+        assert!("k3:red".parse::<Pipeline>().is_err());
+        assert!("conv:x".parse::<Pipeline>().is_err());
+        assert!("conv:red:blue".parse::<Pipeline>().is_err());
+        assert!("".parse::<Pipeline>().is_err());
+        assert!(Cliques::new(5).is_ok());
+        assert!(Cliques::new(6).is_err() && Cliques::new(2).is_err());
+    }
+
+    #[test]
+    fn uniform_graph() {
+        let g = Graph::new(6);
+        let labels = "degree:both,polygons:both,k3,k4,k5,conv:2:both"
+            .parse::<Pipeline>()
+            .unwrap()
+            .label(&g);
+        assert_eq!(labels.len(), 6);
+        assert!(labels.iter().all(|l| *l == labels[0]));
+    }
+
+    #[test]
+    fn separates_degrees() {
+        // every second child of a graph with one fewer vertex has the new vertex
+        // joined to vertex 0, and nothing else
+        let g = Graph::new(3).get_next_size().swap_remove(1);
+        let labels = Pipeline::standard().label(&g);
+        assert_eq!(labels[0], labels[3]);
+        assert_eq!(labels[1], labels[2]);
+        assert_ne!(labels[0], labels[1]);
+    }
+
+    #[test]
+    fn blue_structure() {
+        // a red triangle on 0, 1, 2 and vertex 3 with only blue edges,
+        // so the blue graph is a star centered on 3
+        let g = Graph::from_edges(4, &[(0, 1), (0, 2), (1, 2)]).unwrap();
+        let red = "polygons".parse::<Pipeline>().unwrap().label(&g);
+        let blue = "polygons:blue".parse::<Pipeline>().unwrap().label(&g);
+        let both = "polygons:both".parse::<Pipeline>().unwrap().label(&g);
+        assert_eq!(red[0], red[1]);
+        assert_ne!(red[0], red[3]);
+        assert_eq!(blue[0], blue[2]);
+        assert_ne!(blue[0], blue[3]);
+        assert_ne!(red[3], blue[3]);
+        assert_eq!(both[1], both[2]);
+        assert_ne!(both[2], both[3]);
+    }
+
+    #[test]
+    fn wl_refinement() {
+        // a red path 1 - 0 - 2 - 3, degrees split it into ends and middle,
+        // refinement has nothing left to split
+        let g = Graph::from_edges(4, &[(0, 1), (0, 2), (2, 3)]).unwrap();
+        for spec in ["wl1", "wl2", "degree,wl1"].iter() {
+            let labels = spec.parse::<Pipeline>().unwrap().label(&g);
+            assert_eq!(labels[0], labels[2]);
+            assert_eq!(labels[1], labels[3]);
+            assert_ne!(labels[0], labels[1]);
+        }
+
+        // a red 6-cycle and two red triangles are both 2-regular, 1-WL can't
+        // tell them apart but 2-WL sees the triangles
+        let hexagon = [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0)];
+        let hexagon = Graph::from_edges(6, &hexagon).unwrap();
+        let triangles = [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)];
+        let triangles = Graph::from_edges(6, &triangles).unwrap();
+        let sorted = |spec: &str, g: &Graph| {
+            let mut l = spec.parse::<Pipeline>().unwrap().label(g);
+            l.sort();
+            l
+        };
+        assert_eq!(sorted("wl1", &hexagon), sorted("wl1", &triangles));
+        assert_ne!(sorted("wl2", &hexagon), sorted("wl2", &triangles));
+    }
+
+    #[test]
+    fn deck() {
+        // all four graphs on 3 vertices, by number of red edges
+        let standard = Pipeline::standard();
+        let empty = Graph::new(2).get_next_size();
+        let red = Graph::new(1).get_next_size().swap_remove(1);
+        let mut tier = Tier::default();
+        for &g in [&empty[0], &empty[1], &empty[3], &red.get_next_size()[3]].iter() {
+            tier.insert_checked(g.clone(), &standard);
+        }
+        let deck = Deck {
+            prev: &tier,
+            labeler: &standard,
+        };
+
+        // a red path 1 - 0 - 2 - 3, deleting an end leaves a path, deleting a
+        // middle vertex leaves an edge and an isolated vertex
+        let g = Graph::from_edges(4, &[(0, 1), (0, 2), (2, 3)]).unwrap();
+        let labels = deck.label(&g);
+        assert_eq!(labels[0], labels[2]);
+        assert_eq!(labels[1], labels[3]);
+        assert_ne!(labels[0], labels[1]);
+
+        // relabeling keeps every class, lookups need the labeler the tier was built with
+        let relabeled: Tier<u32> = tier.relabel(&standard);
+        assert_eq!(relabeled.count_graphs(), tier.count_graphs());
+        assert!(relabeled.find(&empty[3], &standard).is_some());
+        assert!(relabeled
+            .find(&empty[3], &Pipeline::default().then(Wl1))
+            .is_none());
+    }
+
+    #[test]
+    fn adaptive_threshold() {
+        // partition {0, 3}, {1, 2}, so complexity 4
+        let g = Graph::new(3).get_next_size().swap_remove(1);
+        let cheap = Pipeline::default().then(Degree(Color::Red));
+        let plain = cheap.label(&g);
+        let lazy = Adaptive {
+            cheap: Pipeline::default().then(Degree(Color::Red)),
+            strong: Pipeline::default().then(Cliques::new(3).unwrap()),
+            threshold: 4,
+        };
+        assert_eq!(lazy.label(&g), plain);
+
+        let eager = Adaptive {
+            threshold: 3,
+            ..lazy
+        };
+        let refined = eager.label(&g);
+        assert_ne!(refined, plain);
+        for i in 0..4 {
+            for j in 0..4 {
+                assert_eq!(refined[i] == refined[j], plain[i] == plain[j]);
+            }
+        }
+    }
+}
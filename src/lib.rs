@@ -4,14 +4,38 @@ extern crate rayon;
 extern crate base64;
 
 mod bitvec;
+pub mod constructions;
+pub mod error;
 pub mod structures;
 
-//#[cfg_attr(vec_graph, path = "graph_vec.rs")]
-//#[cfg_attr(bv_graph, path = "graph_bv.rs")]
-#[path = "graph_bv.rs"]
+// with several features on the bitvec backend wins, then the bitboard one
+#[cfg(not(any(feature = "bv_graph", feature = "bb_graph", feature = "vec_graph")))]
+compile_error!(
+    "enable one of the `bv_graph`, `bb_graph` or `vec_graph` features to pick a Graph backend"
+);
+
+#[cfg_attr(feature = "bv_graph", path = "graph_bv.rs")]
+#[cfg_attr(
+    all(feature = "bb_graph", not(feature = "bv_graph")),
+    path = "graph_bb.rs"
+)]
+#[cfg_attr(
+    not(any(feature = "bv_graph", feature = "bb_graph")),
+    path = "graph_vec.rs"
+)]
 pub mod graph;
 
 pub mod graph_like;
+pub mod invariants;
+pub mod io;
+pub mod labeler;
+pub mod observer;
+pub mod report;
+pub mod sink;
+pub mod stats;
+
+#[cfg(test)]
+mod test_util;
 
 static COMPLEXITIES: &'static [u64] = &[
     1,  // - - - - - - - - - -  0
@@ -44,6 +68,25 @@ pub mod util {
         i
     }
 
+    //number of orderings the iso check may try for a sorted labeling,
+    //the product of the factorials of the label multiplicities
+    pub fn compute_complexity<T: Eq>(labeling_sorted: &[T]) -> u64 {
+        let mut comp = 1u64;
+        let mut current = &labeling_sorted[0];
+        let mut cur_streak = 1;
+        for i in 1..labeling_sorted.len() {
+            if labeling_sorted[i] == *current {
+                cur_streak += 1;
+            } else {
+                current = &labeling_sorted[i];
+                comp *= factorial(&cur_streak);
+                cur_streak = 1;
+            }
+        }
+        comp *= factorial(&cur_streak);
+        comp
+    }
+
     pub fn dec_to_factorial(n: usize, dig: usize, out: &mut Vec<usize>) {
         let mut num = n;
         out.clear();
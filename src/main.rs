@@ -1,46 +1,90 @@
 extern crate graph_lib;
+
+use graph_lib::error::Error;
 use graph_lib::graph::Graph;
+use graph_lib::graph_like::GraphLike;
+use graph_lib::io::{self, ChunkWriter, Format};
+use graph_lib::labeler::{Adaptive, Deck, Labeler, Pipeline};
+use graph_lib::observer::{GenerationObserver, JsonLines, Progress, Silent};
+use graph_lib::stats::TierStats;
+use graph_lib::structures::{Budget, GenerateOptions, Generation, Tier};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
-use std::error::Error;
-use std::fs;
-use std::fs::File;
-use std::io::{BufWriter, Write, BufReader, Read};
-use std::path::Path;
-use graph_lib::structures::*;
-use std::fmt::Debug;
-use std::hash::Hash;
-
-
-fn dump_graph_list<T: Eq + Hash + Debug + Send + Clone + Ord + Sync>(list: &Tier<T>, n: usize) {
-    if list.count_chunks() == 0 {
-        return
-    }
-    println!("{:05?}", list.map.values().next().unwrap().labeling_sorted);
-    let path_str = format!("out/{}.txt", n);
-    let path = Path::new(&path_str);
-    let path_pretty = path.display();
-    fs::create_dir("out");
-    let file = match File::create(&path) {
-        Err(e) => panic!("couldn't create {} : {}", path_pretty, e.description()),
-        Ok(file) => file,
-    };
-    
-    let mut writer = BufWriter::new(&file);
-    for chunk in list.map.values() {
-        let mut buf = String::new();
-        let (last, graphs) = chunk.checked.split_last().unwrap();
-        for graph in graphs.iter() {
-            graph.inner.to_string_append(&mut buf);
-            buf.push_str(";");
-        }
-        last.inner.to_string_append(&mut buf);
-        buf.push_str("\n");
-        writer.write(&buf.into_bytes());
-        
-    }
-    writer.flush();
+const USAGE: &str = "\
+usage: graph_lib [generate] [options]
+       graph_lib extend <file> [num_verts] [options]
+       graph_lib diff <a> <b> <num_verts> [--labeler spec]
+       graph_lib stats <file> [num_verts] [--json] [--labeler spec]
+       graph_lib help
+
+generate writes every tier of (s, t) Ramsey colorings up to --max-verts vertices
+extend does the same from the tier in file, e.g. one saved by an earlier run or
+a hand picked set of graphs, isomorphic copies in it are dropped. Labels stored
+in a .lbl file are kept unless --relabel is given
+diff lists the graphs found in only one of two tier files, up to isomorphism,
+and exits with 1 if there are any
+stats summarizes a tier file: chunk sizes and complexities under the labeler,
+red edge counts, degree sequences and automorphism group sizes
+
+options:
+  --max-verts N          last tier to generate (default 20)
+  --ramsey S,T           no red K_S and no blue K_T, from 3 to 7 (default 4,4)
+  --out DIR              directory the tiers go to as <n>.<format extension> (default out)
+  --format b64|g6|lbl    tier file format, lbl stores vertex labels (default b64)
+  --threads N            generation worker threads (default 4)
+  --progress bar|json|none
+                         how generation reports progress, json writes a line of
+                         JSON to stderr for every event (default bar)
+  --start FILE           start from a tier file, the same as extend FILE
+  --start-verts N        vertices of the graphs in a base64 --start file, when its
+                         name is not <n>.txt
+  --relabel              label a .lbl start file again instead of using its labels
+  --time-limit SECS      stop after SECS seconds, writing what the current tier has
+                         as <n>.partial and its parents not yet extended as
+                         <n-1>.remaining, and exit with 2
+  --partial FILE         merge the graphs of FILE, a .partial tier, into the first
+                         tier, to resume a stopped run from its .remaining file
+  --passes N             write the last tier as it is generated in N passes, each
+                         keeping about 1/N of it in memory, without a time limit
+  --labeler SPEC         comma separated labeler stages, e.g. degree,polygons,k3,conv:2
+  --refine-threshold N   relabel chunks with complexity above N using --refine-labeler
+  --refine-labeler SPEC  (default k3,k4,conv:2)
+  --deck                 label each vertex by the class of its deleted subgraph in
+                         the previous tier
+  --json                 print stats as JSON
+";
+
+const VALUE_FLAGS: &[&str] = &[
+    "--max-verts",
+    "--ramsey",
+    "--out",
+    "--format",
+    "--threads",
+    "--progress",
+    "--time-limit",
+    "--partial",
+    "--passes",
+    "--start",
+    "--start-verts",
+    "--labeler",
+    "--refine-threshold",
+    "--refine-labeler",
+];
+
+const SWITCHES: &[&str] = &["--deck", "--json", "--relabel"];
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("run `graph_lib help` for usage");
+    process::exit(1);
 }
+
 fn fmt_dur(d: &Duration) -> String {
     let hours = d.as_secs() / 3600;
     let mins = d.as_secs() / 60 % 60;
@@ -49,87 +93,413 @@ fn fmt_dur(d: &Duration) -> String {
         hours,
         mins,
         d.as_secs() % 60,
-        d.subsec_nanos() / 1_000_000
+        d.subsec_millis()
     )
 }
 
+// the arguments after the subcommand, split into flags and positionals
+struct Args {
+    values: HashMap<&'static str, String>,
+    switches: Vec<&'static str>,
+    positional: Vec<String>,
+}
 
-use graph_lib::graph_like::GraphLike;
-fn main() {
-    let root = graph_lib::graph::Graph::new(1);
-    let root_tier = graph_lib::structures::Tier::from_graph(root, |_| vec!(0));
-    let mut tiers = vec![root_tier];
-    for i in 0..20 {
-        let start = Instant::now();
-        tiers.push(tiers[i].generate_next_size(&|g: &Graph, b: &mut Vec<Vec<(u32, u32)>>| g.label(b)));
+impl Args {
+    fn parse(args: &[String]) -> Self {
+        let mut out = Args {
+            values: HashMap::new(),
+            switches: vec![],
+            positional: vec![],
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(flag) = VALUE_FLAGS.iter().find(|f| *f == arg) {
+                match iter.next() {
+                    Some(v) => {
+                        out.values.insert(flag, v.clone());
+                    }
+                    None => fail(&format!("{} needs a value", flag)),
+                }
+            } else if let Some(switch) = SWITCHES.iter().find(|f| *f == arg) {
+                out.switches.push(switch);
+            } else if arg.starts_with("--") {
+                fail(&format!("unknown option {}", arg));
+            } else {
+                out.positional.push(arg.clone());
+            }
+        }
+        out
+    }
+
+    fn get(&self, flag: &str) -> Option<&str> {
+        self.values.get(flag).map(String::as_str)
+    }
+
+    fn has(&self, switch: &str) -> bool {
+        self.switches.contains(&switch)
+    }
+
+    // the value of flag parsed as a V, default if it is not given
+    fn parse_or<V: FromStr>(&self, flag: &str, default: V) -> V
+    where
+        V::Err: std::fmt::Display,
+    {
+        match self.get(flag) {
+            Some(v) => v
+                .parse()
+                .unwrap_or_else(|e| fail(&format!("invalid {} `{}`: {}", flag, v, e))),
+            None => default,
+        }
+    }
+}
+
+fn parse_pipeline(spec: &str, flag: &str) -> Pipeline {
+    spec.parse()
+        .unwrap_or_else(|e| fail(&format!("invalid {}: {}", flag, e)))
+}
+
+fn parse_labeler(args: &Args) -> Box<dyn Labeler<u32>> {
+    let cheap = match args.get("--labeler") {
+        Some(spec) => parse_pipeline(spec, "--labeler"),
+        None => Pipeline::standard(),
+    };
+    eprintln!("labeling with {:?}", cheap.stages());
+    if args.get("--refine-threshold").is_none() {
+        return Box::new(cheap);
+    }
+    let threshold: u64 = args.parse_or("--refine-threshold", 0);
+    let spec = args.get("--refine-labeler").unwrap_or("k3,k4,conv:2");
+    let strong = parse_pipeline(spec, "--refine-labeler");
+    eprintln!("refining chunks above {} with {:?}", threshold, strong.stages());
+    Box::new(Adaptive {
+        cheap,
+        strong,
+        threshold,
+    })
+}
+
+fn parse_observer(args: &Args) -> Box<dyn GenerationObserver> {
+    match args.get("--progress").unwrap_or("bar") {
+        "bar" => Box::new(Progress::new()),
+        "json" => Box::new(JsonLines::new(std::io::stderr())),
+        "none" => Box::new(Silent),
+        p => fail(&format!("invalid --progress `{}`, expected bar, json or none", p)),
+    }
+}
+
+fn parse_options(args: &Args) -> GenerateOptions {
+    let mut options = GenerateOptions::default();
+    if let Some(spec) = args.get("--ramsey") {
+        let params: Vec<u32> = spec
+            .split(',')
+            .map(|p| p.trim().parse().ok().filter(|p| (3..=7).contains(p)))
+            .collect::<Option<_>>()
+            .unwrap_or_else(|| fail(&format!("invalid --ramsey `{}`, expected S,T from 3 to 7", spec)));
+        match params[..] {
+            [s, t] => {
+                options.s = s;
+                options.t = t;
+            }
+            _ => fail(&format!("invalid --ramsey `{}`, expected S,T", spec)),
+        }
+    }
+    options.threads = args.parse_or("--threads", options.threads);
+    if options.threads == 0 {
+        fail("--threads has to be at least 1");
+    }
+    options
+}
+
+fn is_labeled(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "lbl")
+}
+
+// a tier file and the number of vertices of its graphs, which base64 files
+// need given or as their name, <n>.txt. The labels of a .lbl file are kept
+fn read_tier(path: &str, num_verts: Option<usize>, labeler: &dyn Labeler<u32>) -> (Tier<u32>, usize) {
+    let path = Path::new(path);
+    let num_verts = num_verts.unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.split('.').next())
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    });
+    let tier: Tier<u32> = io::read_tier_file(path, num_verts, labeler)
+        .unwrap_or_else(|e| fail(&format!("couldn't read {}: {}", path.display(), e)));
+    let n = match tier.graphs().next() {
+        Some(g) => g.num_verts(),
+        None => fail(&format!("{} holds no graphs", path.display())),
+    };
+    (tier, n)
+}
+
+fn write_tier(tier: &Tier<u32>, dir: &Path, n: usize, format: Format) {
+    write_tier_to(tier, &dir.join(format!("{}.{}", n, format.extension())), format);
+}
+
+fn write_tier_to(tier: &Tier<u32>, path: &Path, format: Format) {
+    let written = File::create(path)
+        .map_err(Error::from)
+        .and_then(|file| io::write_tier(tier, BufWriter::new(file), format));
+    if let Err(e) = written {
+        fail(&format!("couldn't write {}: {}", path.display(), e));
+    }
+}
+
+// the tier generation starts from, as a saved tier, deduplicated unless its
+// labels are kept, or a single vertex
+fn start_tier(
+    path: Option<&str>,
+    num_verts: Option<usize>,
+    args: &Args,
+    labeler: &dyn Labeler<u32>,
+) -> (Tier<u32>, usize) {
+    let path = match path {
+        Some(path) => path,
+        None => return (Tier::from_graph(Graph::new(1), labeler), 1),
+    };
+    let (tier, n) = read_tier(path, num_verts, labeler);
+    if is_labeled(Path::new(path)) && !args.has("--relabel") {
+        return (tier, n);
+    }
+    let read = tier.count_graphs().0;
+    let tier = Tier::from_graphs(tier.graphs().cloned(), labeler);
+    let dropped = read - tier.count_graphs().0;
+    if dropped > 0 {
+        eprintln!("dropped {} isomorphic copies from {}", dropped, path);
+    }
+    (tier, n)
+}
+
+fn generate(args: &Args) {
+    if !args.positional.is_empty() {
+        fail(&format!("unexpected argument {}", args.positional[0]));
+    }
+    let num_verts = args.get("--start-verts").map(|_| args.parse_or("--start-verts", 0));
+    run(args, args.get("--start"), num_verts);
+}
+
+fn extend(args: &Args) {
+    if args.get("--start").is_some() {
+        fail("extend takes its tier file as an argument, not --start");
+    }
+    match &args.positional[..] {
+        [path] => run(args, Some(path), None),
+        [path, n] => {
+            let n = n
+                .parse()
+                .unwrap_or_else(|e| fail(&format!("invalid num_verts `{}`: {}", n, e)));
+            run(args, Some(path), Some(n))
+        }
+        _ => fail("extend needs <file> [num_verts]"),
+    }
+}
+
+fn run(args: &Args, start: Option<&str>, num_verts: Option<usize>) {
+    let max_verts: usize = args.parse_or("--max-verts", 20);
+    let options = parse_options(args);
+    let format: Format = args.parse_or("--format", Format::Base64);
+    let dir = Path::new(args.get("--out").unwrap_or("out"));
+    let labeler = parse_labeler(args);
+    let deck = args.has("--deck");
+    let observer = parse_observer(args);
+
+    let (mut tier, mut n) = start_tier(start, num_verts, args, &*labeler);
+    if let Err(e) = fs::create_dir_all(dir) {
+        fail(&format!("couldn't create {}: {}", dir.display(), e));
+    }
+    println!(
+        "generating ({}, {}) colorings from {} graphs on {} vertices up to {} vertices",
+        options.s,
+        options.t,
+        tier.count_graphs().0,
+        n,
+        max_verts
+    );
+    // the children of the parents a stopped run finished, merged into the first
+    // tier this one generates
+    let mut partial = args.get("--partial").map(|path| {
+        let (partial, partial_n) = read_tier(path, Some(n + 1), &*labeler);
+        if partial_n != n + 1 {
+            fail(&format!("{} holds graphs on {} vertices, not {}", path, partial_n, n + 1));
+        }
+        partial
+    });
+    let budget = Budget {
+        cancel: None,
+        deadline: args
+            .get("--time-limit")
+            .map(|_| Instant::now() + Duration::from_secs(args.parse_or("--time-limit", 0))),
+    };
+    let passes = args.get("--passes").map(|_| args.parse_or("--passes", 1));
+    if passes == Some(0) {
+        fail("--passes has to be at least 1");
+    }
+    if passes.is_some() && deck {
+        fail("--passes does not work with --deck");
+    }
+    while n < max_verts {
+        if let (Some(passes), None, true) = (passes, &partial, n + 1 == max_verts) {
+            stream_last(&tier, &*labeler, &options, &*observer, passes, dir, n + 1, format);
+            return;
+        }
+        let mut generation = if deck {
+            let deck = Deck {
+                prev: &tier,
+                labeler: &*labeler,
+            };
+            let mut generation = tier.generate_next_size_budgeted(&deck, &options, &*observer, &budget);
+            generation.tier = generation.tier.relabel(&*labeler);
+            generation
+        } else {
+            tier.generate_next_size_budgeted(&*labeler, &options, &*observer, &budget)
+        };
+        if let Some(partial) = partial.take() {
+            generation.tier = generation.tier.merge(&partial, &*labeler);
+        }
+        if generation.cancelled {
+            stop(&generation, &tier, dir, n, format);
+        }
+        let Generation { tier: next, report, .. } = generation;
+        n += 1;
         println!(
-            "There are {:?} graphs on {} vertices, distinguished into {} classes, generated in {}s",
-            tiers[i + 1].count_graphs(),
-            i + 2,
-            tiers[i + 1].count_chunks(),
-            start.elapsed().as_secs(),
+            "{} vertices: {} graphs in {} chunks, {}",
+            n,
+            next.count_graphs().0,
+            next.count_chunks(),
+            fmt_dur(&report.total)
         );
-        if tiers[i + 1].count_graphs().0 == 0 {
-            println!("stopping");
-            return
-        }
-        println!("Writing to disk...");
-        dump_graph_list(&tiers[i + 1], i + 2);
-        if i > 1 {
-            tiers[i].map.clear();
-            tiers[i].map.shrink_to_fit();
+        print!("{}", report);
+        if next.count_graphs().0 == 0 {
+            println!("no colorings on {} vertices, done", n);
+            return;
         }
-        println!("Done. Starting next tier");
+        write_tier(&next, dir, n, format);
+        tier = next;
     }
-    /*for i in 2..11 {
-        read_graph_tier(i);
-    }*/
 }
 
-use std::collections::HashMap;
-use std::io::BufRead;
+// generates the tier on n vertices in passes, writing its chunks as they come
+// instead of keeping it
+#[allow(clippy::too_many_arguments)]
+fn stream_last(
+    tier: &Tier<u32>,
+    labeler: &dyn Labeler<u32>,
+    options: &GenerateOptions,
+    observer: &dyn GenerationObserver,
+    passes: usize,
+    dir: &Path,
+    n: usize,
+    format: Format,
+) {
+    let start = Instant::now();
+    let path = dir.join(format!("{}.{}", n, format.extension()));
+    let file = File::create(&path).unwrap_or_else(|e| fail(&format!("couldn't write {}: {}", path.display(), e)));
+    let mut sink = ChunkWriter::new(BufWriter::new(file), format);
+    let classes = tier.generate_next_size_streamed(labeler, options, observer, passes, &mut sink);
+    if let Err(e) = sink.finish() {
+        fail(&format!("couldn't write {}: {}", path.display(), e));
+    }
+    println!(
+        "{} vertices: {} graphs in {} passes, {}",
+        n,
+        classes,
+        passes,
+        fmt_dur(&start.elapsed())
+    );
+}
 
-fn read_graph_tier(n: u32) -> HashMap<Vec<u32>, u32> {
-    let mut out = HashMap::new();
-    let path_str = format!("out/{}.txt", n);
-    let path = Path::new(&path_str);
-    let path_pretty = path.display();
+// writes what a generation stopped by --time-limit found and the parents it
+// did not get to, then exits with 2
+fn stop(generation: &Generation<u32>, parents: &Tier<u32>, dir: &Path, n: usize, format: Format) -> ! {
+    let ext = format.extension();
+    let partial = dir.join(format!("{}.partial.{}", n + 1, ext));
+    let remaining = dir.join(format!("{}.remaining.{}", n, ext));
+    write_tier_to(&generation.tier, &partial, format);
+    write_tier_to(&generation.remaining(parents), &remaining, format);
+    println!(
+        "time limit reached after {} of {} chunks on {} vertices, resume with\n  \
+         graph_lib extend {} --partial {}",
+        generation.completed.len(),
+        parents.count_chunks(),
+        n,
+        remaining.display(),
+        partial.display()
+    );
+    process::exit(2);
+}
 
-    let file = match File::open(&path) {
-        Err(e) => panic!("couldn't open {} : {}", path_pretty, e.description()),
-        Ok(file) => file,
+fn diff_tiers(args: &Args) {
+    let (a, b, n) = match &args.positional[..] {
+        [a, b, n] => (a, b, n),
+        _ => fail("diff needs <a> <b> <num_verts>"),
     };
-    
-    let mut reader = BufReader::new(&file);
-    let mut buf = vec!(Vec::with_capacity(n as usize); n as usize);
-    for line in reader.lines() {
-        for graph_b64 in line.unwrap().split(|c| c == ';' || c == '{' || c == '}') {
-            if graph_b64.len() < 2 {
-                continue
-            }
-            let g = Graph::from_str(&graph_b64, n as usize).unwrap();
-            let mut l = g.label(&mut buf);
-            l.sort();
-            *out.entry(l).or_insert(0) += 1;
-            //println!("{:?}", g.label(&mut buf));
+    let n: usize = n
+        .parse()
+        .unwrap_or_else(|e| fail(&format!("invalid num_verts `{}`: {}", n, e)));
+    let labeler = parse_labeler(args);
+    let read = |path: &String| -> Tier<u32> {
+        let tier: Tier<u32> = io::read_tier_file(Path::new(path), n, &*labeler)
+            .unwrap_or_else(|e| fail(&format!("couldn't read {}: {}", path, e)));
+        // stored labels may come from another labeler
+        if is_labeled(Path::new(path)) {
+            tier.relabel(&*labeler)
+        } else {
+            tier
+        }
+    };
+    let tier_a = read(a);
+    let tier_b = read(b);
+    let only_a = tier_a.diff(&tier_b, &*labeler);
+    let only_b = tier_b.diff(&tier_a, &*labeler);
+    println!(
+        "{}: {} graphs, {}: {} graphs, {} in both",
+        a,
+        tier_a.count_graphs().0,
+        b,
+        tier_b.count_graphs().0,
+        tier_a.count_graphs().0 - only_a.count_graphs().0
+    );
+    for (path, only, mark) in [(a, &only_a, '<'), (b, &only_b, '>')].iter() {
+        println!("only in {}: {}", path, only.count_graphs().0);
+        for g in only.graphs() {
+            println!("{} {}", mark, g);
         }
     }
-    let mut histo = HashMap::new();
-    let mut chunks = 0u32;
-    for chunk in out.iter() {
-        chunks += 1;
-        *histo.entry(chunk.1).or_insert(0) += 1;
+    if only_a.count_graphs().0 + only_b.count_graphs().0 > 0 {
+        process::exit(1);
     }
-    let mut results = vec!();
+}
 
-    for v in histo.iter() {
-        results.push((v.0, v.1));
+fn stats(args: &Args) {
+    let (path, num_verts) = match &args.positional[..] {
+        [path] => (path, None),
+        [path, n] => (
+            path,
+            Some(n.parse().unwrap_or_else(|e| fail(&format!("invalid num_verts `{}`: {}", n, e)))),
+        ),
+        _ => fail("stats needs <file> [num_verts]"),
+    };
+    let labeler = parse_labeler(args);
+    let (tier, _) = read_tier(path, num_verts, &*labeler);
+    let stats = TierStats::of(&tier);
+    if args.has("--json") {
+        println!("{}", stats.to_json());
+    } else {
+        print!("{}", stats);
     }
-    results.sort();
+}
 
-    for v in results.iter() {
-        println!("There are {} chunks with {} graphs", v.1, v.0);
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("help") | Some("--help") | Some("-h") => print!("{}", USAGE),
+        Some("diff") => diff_tiers(&Args::parse(&args[1..])),
+        Some("stats") => stats(&Args::parse(&args[1..])),
+        Some("generate") => generate(&Args::parse(&args[1..])),
+        Some("extend") => extend(&Args::parse(&args[1..])),
+        Some(cmd) if !cmd.starts_with("--") => fail(&format!("unknown command {}", cmd)),
+        _ => generate(&Args::parse(&args)),
     }
-    println!("{}", chunks);
-    out
 }
@@ -0,0 +1,221 @@
+// What generation reports while it runs, see `Tier::generate_next_size_observed`
+
+use indicatif::ProgressBar;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Something generation did, in the order it happens for a single size.
+/// Workers report their chunks and flushes concurrently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Generation of the next size starts from `graphs` graphs in `chunks` chunks
+    Started { graphs: usize, chunks: usize },
+    /// A worker extended every graph of a parent chunk, keeping `children`
+    ChunkProcessed { children: usize },
+    /// A worker moved its children into the shared tier, the `cycle`th time
+    /// any worker did
+    Flushed { cycle: u64 },
+    /// A cleaning pass over the shared tier while workers run, after which it
+    /// holds `graphs` classes, `added` more than after the last pass
+    Cleaned {
+        pass: u64,
+        graphs: usize,
+        chunks: usize,
+        added: usize,
+    },
+    /// The budget ran out with `completed` of the `chunks` parent chunks done,
+    /// the children found so far are still cleaned
+    Cancelled { completed: usize, chunks: usize },
+    /// Every worker is done and the last cleaning pass starts
+    FinalCleanup { chunks: usize },
+    /// A chunk whose cleanup may take long, its complexity is above 10^7
+    SlowClean {
+        checked: usize,
+        unchecked: usize,
+        comp: u64,
+    },
+    /// The next size is done
+    Finished { graphs: usize, chunks: usize },
+}
+
+impl Event {
+    /// The event as a single line JSON object, its name under "event"
+    pub fn to_json(&self) -> String {
+        match *self {
+            Event::Started { graphs, chunks } => format!(
+                "{{\"event\": \"started\", \"graphs\": {}, \"chunks\": {}}}",
+                graphs, chunks
+            ),
+            Event::ChunkProcessed { children } => format!(
+                "{{\"event\": \"chunk_processed\", \"children\": {}}}",
+                children
+            ),
+            Event::Flushed { cycle } => {
+                format!("{{\"event\": \"flushed\", \"cycle\": {}}}", cycle)
+            }
+            Event::Cleaned {
+                pass,
+                graphs,
+                chunks,
+                added,
+            } => format!(
+                "{{\"event\": \"cleaned\", \"pass\": {}, \"graphs\": {}, \"chunks\": {}, \"added\": {}}}",
+                pass, graphs, chunks, added
+            ),
+            Event::Cancelled { completed, chunks } => format!(
+                "{{\"event\": \"cancelled\", \"completed\": {}, \"chunks\": {}}}",
+                completed, chunks
+            ),
+            Event::FinalCleanup { chunks } => {
+                format!("{{\"event\": \"final_cleanup\", \"chunks\": {}}}", chunks)
+            }
+            Event::SlowClean {
+                checked,
+                unchecked,
+                comp,
+            } => format!(
+                "{{\"event\": \"slow_clean\", \"checked\": {}, \"unchecked\": {}, \"comp\": {}}}",
+                checked, unchecked, comp
+            ),
+            Event::Finished { graphs, chunks } => format!(
+                "{{\"event\": \"finished\", \"graphs\": {}, \"chunks\": {}}}",
+                graphs, chunks
+            ),
+        }
+    }
+}
+
+/// Receives the events of generation, from several threads at once
+pub trait GenerationObserver: Sync {
+    fn observe(&self, event: Event);
+}
+
+/// Ignores every event
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Silent;
+
+impl GenerationObserver for Silent {
+    fn observe(&self, _: Event) {}
+}
+
+/// A progress bar over the parent chunks, with a line for every cleaning pass
+/// and slow chunk. What generation always printed before observers
+#[derive(Debug)]
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Progress {
+            bar: ProgressBar::new(0),
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress::new()
+    }
+}
+
+impl GenerationObserver for Progress {
+    fn observe(&self, event: Event) {
+        match event {
+            Event::Started { chunks, .. } => {
+                self.bar.set_length(chunks as u64);
+                self.bar.set_position(0);
+            }
+            Event::ChunkProcessed { .. } => self.bar.inc(1),
+            Event::Cleaned {
+                pass,
+                graphs,
+                chunks,
+                added,
+            } => self.bar.println(format!(
+                "{} graphs and {} chunks\n+{} graphs, cleaning {}\n",
+                graphs, chunks, added, pass
+            )),
+            Event::Cancelled { completed, chunks } => self.bar.println(format!(
+                "stopped after {} of {} chunks",
+                completed, chunks
+            )),
+            Event::FinalCleanup { .. } => {
+                self.bar.finish();
+                println!("final cleanup");
+            }
+            Event::SlowClean {
+                checked,
+                unchecked,
+                comp,
+            } => println!("cleaning {}, {}, comp {}", checked, unchecked, comp),
+            Event::Flushed { .. } | Event::Finished { .. } => {}
+        }
+    }
+}
+
+/// Writes every event as a line of JSON, with the milliseconds since the log
+/// was created under "ms"
+#[derive(Debug)]
+pub struct JsonLines<W> {
+    start: Instant,
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLines<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLines {
+            start: Instant::now(),
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+impl<W: Write + Send> GenerationObserver for JsonLines<W> {
+    fn observe(&self, event: Event) {
+        let json = event.to_json();
+        let line = format!(
+            "{{\"ms\": {}, {}\n",
+            self.start.elapsed().as_millis(),
+            &json[1..]
+        );
+        // a log that can't be written is no reason to stop generating
+        let _ = self.writer.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::Pipeline;
+    use crate::structures::GenerateOptions;
+    use crate::test_util::tier_up_to;
+
+    #[test]
+    fn json_lines() {
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(4, &labeler);
+        let log = JsonLines::new(vec![]);
+        let next = tier.generate_next_size_observed(&labeler, &GenerateOptions::default(), &log);
+        let log = String::from_utf8(log.into_inner()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+
+        assert!(lines.iter().all(|l| l.starts_with("{\"ms\": ") && l.ends_with('}')));
+        assert!(lines[0].ends_with(&format!(
+            "\"event\": \"started\", \"graphs\": 9, \"chunks\": {}}}",
+            tier.count_chunks()
+        )));
+        let processed = lines.iter().filter(|l| l.contains("chunk_processed")).count();
+        assert_eq!(processed, tier.count_chunks());
+        assert!(lines[lines.len() - 2].contains("\"event\": \"final_cleanup\""));
+        assert!(lines[lines.len() - 1].ends_with(&format!(
+            "\"event\": \"finished\", \"graphs\": 24, \"chunks\": {}}}",
+            next.count_chunks()
+        )));
+    }
+}
@@ -0,0 +1,150 @@
+// What generating one size cost, see `Tier::generate_next_size_report`
+
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// How many of the most expensive chunks a report keeps
+pub const EXPENSIVE_CHUNKS: usize = 10;
+
+/// A chunk of the new tier as its final cleanup saw it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkCost {
+    /// `Chunk::comp`
+    pub comp: u64,
+    /// Graphs in the chunk before the final cleanup, classes and candidates
+    pub candidates: usize,
+    /// Classes left after it
+    pub graphs: usize,
+    pub cleaning: Duration,
+}
+
+/// Counts and timings of generating one size. The extension, labeling and
+/// merging times are summed over the workers, so with several they add up to
+/// more than `total`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenerationReport {
+    /// Children of every parent graph, before any filtering
+    pub descendants: u64,
+    /// Children with a red K_s or a blue K_t
+    pub rejected: u64,
+    /// Children whose chunk belongs to another pass of
+    /// `Tier::generate_next_size_streamed`, dropped right after labeling
+    pub skipped: u64,
+    /// Children dropped by `Chunk::clean_isos` as copies of another
+    pub duplicates: u64,
+    /// Building the children and checking them for cliques
+    pub extension: Duration,
+    /// Running the labeler on the children that passed the clique check
+    pub labeling: Duration,
+    /// Moving the children of workers into the shared tier, waiting for it included
+    pub merging: Duration,
+    /// The cleaning passes while workers run and the final cleanup
+    pub cleaning: Duration,
+    pub total: Duration,
+    /// The most unchecked graphs the shared tier held at once
+    pub peak_unchecked: usize,
+    /// The chunks with the highest `comp`, highest first
+    pub expensive_chunks: Vec<ChunkCost>,
+}
+
+impl GenerationReport {
+    /// Children that passed the clique filter and went into the new tier
+    pub fn kept(&self) -> u64 {
+        self.descendants - self.rejected - self.skipped
+    }
+
+    pub fn to_json(&self) -> String {
+        let chunks: Vec<String> = self
+            .expensive_chunks
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"comp\": {}, \"candidates\": {}, \"graphs\": {}, \"cleaning_ms\": {}}}",
+                    c.comp,
+                    c.candidates,
+                    c.graphs,
+                    c.cleaning.as_millis()
+                )
+            })
+            .collect();
+        format!(
+            "{{\"descendants\": {}, \"rejected\": {}, \"skipped\": {}, \"duplicates\": {}, \
+             \"extension_ms\": {}, \"labeling_ms\": {}, \"merging_ms\": {}, \"cleaning_ms\": {}, \
+             \"total_ms\": {}, \"peak_unchecked\": {}, \"expensive_chunks\": [{}]}}",
+            self.descendants,
+            self.rejected,
+            self.skipped,
+            self.duplicates,
+            self.extension.as_millis(),
+            self.labeling.as_millis(),
+            self.merging.as_millis(),
+            self.cleaning.as_millis(),
+            self.total.as_millis(),
+            self.peak_unchecked,
+            chunks.join(", ")
+        )
+    }
+}
+
+impl Display for GenerationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "  {} descendants, {} rejected, {} duplicates, peak {} unchecked",
+            self.descendants, self.rejected, self.duplicates, self.peak_unchecked
+        )?;
+        if self.skipped > 0 {
+            writeln!(f, "  {} in other passes", self.skipped)?;
+        }
+        writeln!(
+            f,
+            "  extension {:?}, labeling {:?}, merging {:?}, cleaning {:?}, total {:?}",
+            self.extension, self.labeling, self.merging, self.cleaning, self.total
+        )?;
+        // a line for the three worst, the json has them all
+        let worst: Vec<String> = self
+            .expensive_chunks
+            .iter()
+            .take(3)
+            .map(|c| format!("{} ({} -> {} in {:?})", c.comp, c.candidates, c.graphs, c.cleaning))
+            .collect();
+        if !worst.is_empty() {
+            writeln!(f, "  most expensive chunks: {}", worst.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::Pipeline;
+    use crate::observer::Silent;
+    use crate::structures::{GenerateOptions, Generation};
+    use crate::test_util::tier_up_to;
+
+    #[test]
+    fn counts() {
+        let labeler = Pipeline::standard();
+        let options = GenerateOptions::default();
+        let tier = tier_up_to(5, &labeler);
+        let Generation { tier: next, report, .. } =
+            tier.generate_next_size_report(&labeler, &options, &Silent);
+
+        // every graph on 5 vertices has 2^5 children
+        assert_eq!(report.descendants, 24 * 32);
+        assert_eq!(report.kept() - report.duplicates, 84);
+        assert_eq!(next.count_graphs().0, 84);
+        assert!(report.rejected > 0 && report.duplicates > 0);
+        assert_eq!(report.skipped, 0);
+        assert_eq!(report.peak_unchecked as u64, report.kept());
+        assert!(report.total >= report.cleaning);
+
+        let chunks = &report.expensive_chunks;
+        assert_eq!(chunks.len(), EXPENSIVE_CHUNKS.min(next.count_chunks()));
+        assert!(chunks.windows(2).all(|w| w[0].comp >= w[1].comp));
+        let top = next.map.values().map(|c| c.comp).max().unwrap();
+        assert_eq!(chunks[0].comp, top);
+        assert!(report.to_json().contains("\"descendants\": 768, "));
+    }
+}
@@ -0,0 +1,72 @@
+// Where `Tier::generate_next_size_streamed` puts the chunks it cleaned
+
+use crate::structures::Chunk;
+use std::hash::Hash;
+use std::sync::mpsc::{Sender, SyncSender};
+
+/// Takes the chunks of a tier one at a time, each with its classes in
+/// `checked`. No two chunks share a label
+pub trait ChunkSink<T: Hash, G> {
+    fn chunk(&mut self, chunk: Chunk<T, G>);
+}
+
+impl<T: Hash, G, F: FnMut(Chunk<T, G>)> ChunkSink<T, G> for F {
+    fn chunk(&mut self, chunk: Chunk<T, G>) {
+        self(chunk)
+    }
+}
+
+// chunks sent after the receiver hung up are dropped
+impl<T: Hash, G> ChunkSink<T, G> for Sender<Chunk<T, G>> {
+    fn chunk(&mut self, chunk: Chunk<T, G>) {
+        let _ = self.send(chunk);
+    }
+}
+
+/// Blocks while the channel is full, which keeps generation from running
+/// ahead of a slow receiver
+impl<T: Hash, G> ChunkSink<T, G> for SyncSender<Chunk<T, G>> {
+    fn chunk(&mut self, chunk: Chunk<T, G>) {
+        let _ = self.send(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::Pipeline;
+    use crate::observer::Silent;
+    use crate::structures::GenerateOptions;
+    use crate::test_util::tier_up_to;
+    use std::collections::HashSet;
+    use std::sync::mpsc;
+
+    #[test]
+    fn streamed() {
+        let labeler = Pipeline::standard();
+        let options = GenerateOptions::default();
+        let tier = tier_up_to(5, &labeler);
+        let whole = tier.generate_next_size(&labeler);
+
+        for &passes in [1, 3].iter() {
+            let mut chunks = vec![];
+            let mut collect = |chunk: Chunk<u32>| chunks.push(chunk);
+            let classes =
+                tier.generate_next_size_streamed(&labeler, &options, &Silent, passes, &mut collect);
+            assert_eq!(classes, 84);
+            let labels: HashSet<_> = chunks.iter().map(|c| c.labeling_sorted.clone()).collect();
+            assert_eq!(labels.len(), chunks.len());
+            assert_eq!(labels.len(), whole.count_chunks());
+            for chunk in chunks.iter() {
+                assert_eq!(chunk.checked.len(), whole.map[&chunk.labeling_sorted].checked.len());
+                assert!(chunk.unchecked.is_empty());
+            }
+        }
+
+        let (mut sender, receiver) = mpsc::channel();
+        let classes = tier.generate_next_size_streamed(&labeler, &options, &Silent, 2, &mut sender);
+        drop(sender);
+        let received: usize = receiver.iter().map(|c: Chunk<u32>| c.checked.len()).sum();
+        assert_eq!((classes, received), (84, 84));
+    }
+}
@@ -0,0 +1,131 @@
+// Summaries of a tier, what the `stats` command prints
+
+use crate::graph_like::{Extendable, GraphLike};
+use crate::invariants;
+use crate::structures::Tier;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+/// Counts and distributions over the graphs of a tier, every histogram maps a
+/// value to how many chunks or graphs have it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TierStats {
+    pub graphs: usize,
+    pub chunks: usize,
+    /// Graphs per chunk
+    pub chunk_sizes: BTreeMap<usize, usize>,
+    /// `Chunk::comp`, the number of vertex permutations an iso check may try
+    pub complexities: BTreeMap<u64, usize>,
+    pub red_edges: BTreeMap<usize, usize>,
+    /// Sorted red degrees
+    pub degree_sequences: BTreeMap<Vec<u32>, usize>,
+    /// Size of the automorphism group
+    pub automorphisms: BTreeMap<u64, usize>,
+}
+
+impl TierStats {
+    pub fn of<T, G>(tier: &Tier<T, G>) -> Self
+    where
+        T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        G: GraphLike + Extendable + Clone + Send + Sync,
+    {
+        let mut out = TierStats::default();
+        for chunk in tier.map.values() {
+            out.chunks += 1;
+            *out.chunk_sizes.entry(chunk.checked.len()).or_insert(0) += 1;
+            *out.complexities.entry(chunk.comp).or_insert(0) += 1;
+        }
+        for g in tier.graphs() {
+            out.graphs += 1;
+            let mut degrees = g.label_degrees(true);
+            degrees.sort();
+            let edges = degrees.iter().sum::<u32>() as usize / 2;
+            *out.red_edges.entry(edges).or_insert(0) += 1;
+            *out.degree_sequences.entry(degrees).or_insert(0) += 1;
+            *out
+                .automorphisms
+                .entry(invariants::automorphism_count(g))
+                .or_insert(0) += 1;
+        }
+        out
+    }
+
+    /// The same numbers as a JSON object, histograms become lists of
+    /// `[value, count]` pairs
+    pub fn to_json(&self) -> String {
+        fn pairs<K, F: Fn(&K) -> String>(h: &BTreeMap<K, usize>, key: F) -> String {
+            let items: Vec<String> = h
+                .iter()
+                .map(|(k, count)| format!("[{}, {}]", key(k), count))
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        let sequence = |s: &Vec<u32>| {
+            let items: Vec<String> = s.iter().map(u32::to_string).collect();
+            format!("[{}]", items.join(", "))
+        };
+        format!(
+            "{{\"graphs\": {}, \"chunks\": {}, \"chunk_sizes\": {}, \"complexities\": {}, \
+             \"red_edges\": {}, \"degree_sequences\": {}, \"automorphisms\": {}}}",
+            self.graphs,
+            self.chunks,
+            pairs(&self.chunk_sizes, usize::to_string),
+            pairs(&self.complexities, u64::to_string),
+            pairs(&self.red_edges, usize::to_string),
+            pairs(&self.degree_sequences, sequence),
+            pairs(&self.automorphisms, u64::to_string),
+        )
+    }
+}
+
+fn histogram<K: Debug>(f: &mut fmt::Formatter, title: &str, h: &BTreeMap<K, usize>) -> fmt::Result {
+    writeln!(f, "{}:", title)?;
+    for (k, count) in h.iter() {
+        writeln!(f, "  {:>10} x {:?}", count, k)?;
+    }
+    Ok(())
+}
+
+impl Display for TierStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "graphs: {}", self.graphs)?;
+        writeln!(f, "chunks: {}", self.chunks)?;
+        histogram(f, "chunks by size", &self.chunk_sizes)?;
+        histogram(f, "chunks by complexity", &self.complexities)?;
+        histogram(f, "graphs by red edges", &self.red_edges)?;
+        histogram(f, "graphs by red degree sequence", &self.degree_sequences)?;
+        histogram(f, "graphs by |Aut|", &self.automorphisms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::Pipeline;
+    use crate::test_util::tier_up_to;
+
+    #[test]
+    fn four_vertices() {
+        let labeler = Pipeline::standard();
+        let tier = tier_up_to(4, &labeler);
+        let stats = TierStats::of(&tier);
+        // all 11 graphs on 4 vertices but the empty and the complete one
+        assert_eq!(stats.graphs, 9);
+        assert_eq!(stats.chunk_sizes.values().sum::<usize>(), stats.chunks);
+        assert_eq!(
+            stats.red_edges.into_iter().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2), (3, 3), (4, 2), (5, 1)]
+        );
+        assert_eq!(stats.degree_sequences[&vec![1, 1, 1, 1]], 1);
+        assert_eq!(stats.degree_sequences[&vec![2, 2, 2, 2]], 1);
+        // the 4-cycle and the matching have 8 automorphisms, paths and paws 2
+        assert_eq!(stats.automorphisms[&8], 2);
+        assert_eq!(stats.automorphisms.values().sum::<usize>(), 9);
+
+        let json = TierStats::of(&tier).to_json();
+        assert!(json.starts_with("{\"graphs\": 9, \"chunks\": "));
+        assert!(json.contains("\"red_edges\": [[1, 1], [2, 2], [3, 3], [4, 2], [5, 1]]"));
+        assert!(json.contains("[[1, 1, 1, 1], 1]"));
+    }
+}
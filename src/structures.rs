@@ -1,22 +1,29 @@
 use crate::graph::Graph;
+use crate::labeler::Labeler;
 use crate::util;
-use indicatif::ProgressBar;
+use crate::observer::{Event, GenerationObserver, Progress};
+use crate::report::{ChunkCost, GenerationReport, EXPENSIVE_CHUNKS};
 use rayon::prelude::*;
-use std::collections::HashMap;
-use std::hash::Hash;
+use crate::sink::ChunkSink;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::graph_like::GraphLike;
+use crate::graph_like::{Extendable, GraphLike};
 
 #[derive(Clone, Debug)]
-pub struct Chunk<T: Hash> {
+pub struct Chunk<T: Hash, G = Graph> {
     pub comp: u64,
     pub labeling_sorted: ChunkLabeling<T>,
-    pub checked: Vec<LabeledGraph>,
-    pub unchecked: Vec<Option<LabeledGraph>>,
+    pub checked: Vec<LabeledGraph<G>>,
+    pub unchecked: Vec<Option<LabeledGraph<G>>>,
 }
 
-impl<T: Hash> Default for Chunk<T> {
+impl<T: Hash, G> Default for Chunk<T, G> {
     fn default() -> Self {
         Chunk {
             comp: 0,
@@ -27,7 +34,7 @@ impl<T: Hash> Default for Chunk<T> {
     }
 }
 
-impl<T: Eq + Hash> Chunk<T> {
+impl<T: Eq + Hash, G: GraphLike + Extendable> Chunk<T, G> {
     pub fn clean_isos(&mut self) {
         //two stages, first every checked with every unchecked,
         //then remaining unchecked amongst themselves
@@ -35,7 +42,7 @@ impl<T: Eq + Hash> Chunk<T> {
         for checked_idx in (0..self.checked.len()).rev() {
             for unchecked in self.unchecked.iter_mut() {
                 if let Some(g) = unchecked {
-                    if is_color_iso(&self.checked[checked_idx], &g) {
+                    if is_color_iso(&self.checked[checked_idx], g) {
                         *unchecked = None;
                     }
                 }
@@ -60,7 +67,7 @@ impl<T: Eq + Hash> Chunk<T> {
     }
     
     // returns child graphs that satisfy F
-    pub fn get_next_size<F: Fn(&Graph) -> bool>(self, filter: F) -> Vec<Graph> {
+    pub fn get_next_size<F: Fn(&G) -> bool>(self, filter: F) -> Vec<G> {
         let mut out = vec![];
         for graph in self.checked.iter() {
             out.append(
@@ -76,10 +83,19 @@ impl<T: Eq + Hash> Chunk<T> {
     }
 
     pub fn from_sorted_label(l: ChunkLabeling<T>) -> Self {
-        let mut out = Chunk::default();
-        out.comp = compute_complexity(&l);
-        out.labeling_sorted = l;
-        out
+        Chunk {
+            comp: util::compute_complexity(&l.0),
+            labeling_sorted: l,
+            ..Chunk::default()
+        }
+    }
+
+    /// The vertex labels g was chunked by, g has to be a graph of this chunk
+    pub fn vertex_labels(&self, g: &LabeledGraph<G>) -> Vec<T>
+    where
+        T: Clone,
+    {
+        g.labels.0.iter().map(|&i| self.labeling_sorted.0[i as usize].clone()).collect()
     }
 
     pub fn trim(&mut self) {
@@ -89,11 +105,63 @@ impl<T: Eq + Hash> Chunk<T> {
 }
 
 #[derive(Clone, Debug)]
-pub struct LabeledGraph {
-    pub inner: Graph,
+pub struct LabeledGraph<G = Graph> {
+    pub inner: G,
     pub labels: GraphLabeling,
 }
 
+/// The knobs of `Tier::generate_next_size_with`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GenerateOptions {
+    /// Children with a red K_s are dropped, 3 to 7
+    pub s: u32,
+    /// Children with a blue K_t are dropped, 3 to 7
+    pub t: u32,
+    /// Worker threads, a thread for cleaning up comes on top
+    pub threads: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            s: 4,
+            t: 4,
+            threads: 4,
+        }
+    }
+}
+
+/// Stops a generation from another thread, clones share the flag
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When `Tier::generate_next_size_budgeted` stops before every parent chunk is
+/// done. Workers check it between chunks, the default never stops
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    pub cancel: Option<CancelToken>,
+    pub deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn is_spent(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct ChunkLabeling<T: Hash>(Vec<T>);
 
@@ -101,12 +169,49 @@ pub struct ChunkLabeling<T: Hash>(Vec<T>);
 // label of vertex i = chunklabel[graphlabel[i]]
 pub struct GraphLabeling(Vec<u8>);
 
+/// Every graph of one size, grouped into chunks by their sorted vertex labels
+///
+/// Generic over the graph representation, anything `GraphLike` that can be
+/// extended by a vertex can be generated and deduplicated
+#[derive(Debug)]
+pub struct Tier<T: Hash + Debug + Eq + Send, G = Graph> {
+    pub map: HashMap<ChunkLabeling<T>, Chunk<T, G>>,
+}
+
+/// A tier from `Tier::generate_next_size_report` and what generating it cost
+///
+/// A generation stopped by its `Budget` holds the children of the parent
+/// chunks in `completed` only. Generating from `Generation::remaining` and
+/// merging the two gives the whole tier
 #[derive(Debug)]
-pub struct Tier<T: Hash + Debug + Eq + Send> {
-    pub map: HashMap<ChunkLabeling<T>, Chunk<T>>,
+pub struct Generation<T: Hash + Debug + Eq + Send, G = Graph> {
+    pub tier: Tier<T, G>,
+    pub report: GenerationReport,
+    /// Chunks of the parent tier whose every graph was extended
+    pub completed: Vec<ChunkLabeling<T>>,
+    pub cancelled: bool,
 }
 
-impl<T: Hash + Debug + Eq + Send> Default for Tier<T> {
+impl<T, G> Generation<T, G>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+    G: GraphLike + Extendable + Clone + Send + Sync,
+{
+    /// The chunks of parent, the tier this was generated from, that are not done
+    pub fn remaining(&self, parent: &Tier<T, G>) -> Tier<T, G> {
+        let done: HashSet<&ChunkLabeling<T>> = self.completed.iter().collect();
+        Tier {
+            map: parent
+                .map
+                .iter()
+                .filter(|(label, _)| !done.contains(label))
+                .map(|(label, chunk)| (label.clone(), chunk.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Hash + Debug + Eq + Send, G> Default for Tier<T, G> {
     fn default() -> Self {
         Tier {
             map: HashMap::new(),
@@ -114,7 +219,11 @@ impl<T: Hash + Debug + Eq + Send> Default for Tier<T> {
     }
 }
 
-impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
+impl<T, G> Tier<T, G>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+    G: GraphLike + Extendable + Clone + Send + Sync,
+{
     pub fn count_chunks(&self) -> usize {
         self.map.len()
     }
@@ -136,95 +245,357 @@ impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
             )
         })
     }
-    pub fn from_graph<F: Fn(&Graph) -> Vec<T>>(g: Graph, f: F) -> Self {
+    // every checked graph, chunk by chunk, e.g. for invariants::cospectral_classes
+    pub fn graphs(&self) -> impl Iterator<Item = &G> {
+        self.map
+            .values()
+            .flat_map(|chunk| chunk.checked.iter().map(|g| &g.inner))
+    }
+    pub fn from_graph<L: Labeler<T, G> + ?Sized>(g: G, labeler: &L) -> Self {
         let mut out = Tier::default();
-        let mut label = f(&g);
-        let mut label_sorted = label.clone();
-        label_sorted.sort();
-        let label = normalize(&mut label, &label_sorted);
-        let label_sorted1 = ChunkLabeling(label_sorted);
-        let label_sorted2 = label_sorted1.clone();
-
-        let out_chunk = out
-            .map
-            .entry(label_sorted1)
-            .or_insert_with(|| Chunk::from_sorted_label(label_sorted2));
-
-        out_chunk.checked.push(LabeledGraph {
-            inner: g,
-            labels: GraphLabeling(label),
-        });
+        out.insert_checked(g, labeler);
         out
     }
-    pub fn insert_checked<F: Fn(&Graph) -> Vec<T>>(&mut self, g: Graph, f: F) {
-        let label = f(&g);
-        let mut label_sorted = label.clone();
-        let mut label = label;
-        label_sorted.sort();
-        let label = normalize(&mut label, &label_sorted);
-        let label_sorted1 = ChunkLabeling(label_sorted);
-        let label_sorted2 = label_sorted1.clone();
-
-        let out_chunk = self
-            .map
-            .entry(label_sorted1)
-            .or_insert_with(|| Chunk::from_sorted_label(label_sorted2));
-
-        out_chunk.checked.push(LabeledGraph {
-            inner: g,
-            labels: GraphLabeling(label),
-        });
+    pub fn insert_checked<L: Labeler<T, G> + ?Sized>(&mut self, g: G, labeler: &L) {
+        let (label_sorted, labeled) = label_graph(g, labeler);
+        self.chunk_mut(label_sorted).checked.push(labeled);
+    }
+
+    /// Inserts g with vertex labels already computed, e.g. stored in a tier file,
+    /// instead of running a labeler. They have to be what the tier's labeler gives
+    pub fn insert_labeled(&mut self, g: G, labels: &[T]) {
+        assert_eq!(labels.len(), g.num_verts(), "one label per vertex");
+        let (label_sorted, labeled) = with_labels(g, labels);
+        self.chunk_mut(label_sorted).checked.push(labeled);
+    }
+
+    /// Inserts g unless the tier has an isomorphic copy, returns whether it did
+    pub fn insert_unique<L: Labeler<T, G> + ?Sized>(&mut self, g: G, labeler: &L) -> bool {
+        let (label_sorted, labeled) = label_graph(g, labeler);
+        let chunk = self.chunk_mut(label_sorted);
+        if chunk.checked.iter().any(|h| is_color_iso(h, &labeled)) {
+            return false;
+        }
+        chunk.checked.push(labeled);
+        true
+    }
+
+    /// A tier of the classes of graphs, e.g. a hand picked starting set, with
+    /// isomorphic copies dropped
+    pub fn from_graphs<I, L>(graphs: I, labeler: &L) -> Self
+    where
+        I: IntoIterator<Item = G>,
+        L: Labeler<T, G> + ?Sized,
+    {
+        let mut out = Tier::default();
+        for g in graphs {
+            out.insert_unique(g, labeler);
+        }
+        out
+    }
+
+    fn chunk_mut(&mut self, label_sorted: ChunkLabeling<T>) -> &mut Chunk<T, G> {
+        let label_sorted2 = label_sorted.clone();
+        self.map
+            .entry(label_sorted)
+            .or_insert_with(|| Chunk::from_sorted_label(label_sorted2))
+    }
+
+    // the same tier with every graph labeled by `labeler` instead
+    pub fn relabel<U, L>(&self, labeler: &L) -> Tier<U, G>
+    where
+        U: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        L: Labeler<U, G> + ?Sized,
+    {
+        let mut out = Tier::default();
+        for chunk in self.map.values() {
+            for g in chunk.checked.iter() {
+                out.insert_checked(g.inner.clone(), labeler);
+            }
+        }
+        out
+    }
+
+    /// The chunk and position within it of the graph isomorphic to g, if the tier
+    /// has one. `labeler` has to be the one the tier was built with, otherwise g
+    /// lands in the wrong chunk and is not found
+    pub fn find<L: Labeler<T, G> + ?Sized>(
+        &self,
+        g: &G,
+        labeler: &L,
+    ) -> Option<(ChunkLabeling<T>, usize)> {
+        let (label_sorted, query) = label_graph(g.clone(), labeler);
+        let chunk = self.map.get(&label_sorted)?;
+        chunk
+            .checked
+            .iter()
+            .position(|h| is_color_iso(h, &query))
+            .map(|i| (label_sorted, i))
+    }
+
+    /// The graphs of this tier with no isomorphic copy in other
+    ///
+    /// `labeler` has to be the one this tier was built with, other may come from
+    /// any labeler since it is relabeled first. Same for `intersection` and `merge`
+    pub fn diff<U, L>(&self, other: &Tier<U, G>, labeler: &L) -> Self
+    where
+        U: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        L: Labeler<T, G> + ?Sized,
+    {
+        self.split_by(other, labeler).1
+    }
+
+    /// The graphs of this tier that have an isomorphic copy in other
+    pub fn intersection<U, L>(&self, other: &Tier<U, G>, labeler: &L) -> Self
+    where
+        U: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        L: Labeler<T, G> + ?Sized,
+    {
+        self.split_by(other, labeler).0
+    }
+
+    /// Every graph of this tier, plus those of other it has no copy of
+    pub fn merge<U, L>(&self, other: &Tier<U, G>, labeler: &L) -> Self
+    where
+        U: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        L: Labeler<T, G> + ?Sized,
+    {
+        let mut out = other.relabel(labeler).diff(self, labeler);
+        for (label, chunk) in self.map.iter() {
+            out.map
+                .entry(label.clone())
+                .or_insert_with(|| Chunk::from_sorted_label(label.clone()))
+                .checked
+                .extend(chunk.checked.iter().cloned());
+        }
+        out
+    }
+
+    // (graphs with a copy in other, graphs without), chunk labels stay the same
+    // since other is relabeled to match before comparing
+    fn split_by<U, L>(&self, other: &Tier<U, G>, labeler: &L) -> (Self, Self)
+    where
+        U: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+        L: Labeler<T, G> + ?Sized,
+    {
+        let other = other.relabel(labeler);
+        let mut shared = Tier::default();
+        let mut only = Tier::default();
+        for (label, chunk) in self.map.iter() {
+            let theirs = other.map.get(label);
+            for g in chunk.checked.iter() {
+                let found = theirs.is_some_and(|c| c.checked.iter().any(|h| is_color_iso(h, g)));
+                let side: &mut Self = if found { &mut shared } else { &mut only };
+                side.map
+                    .entry(label.clone())
+                    .or_insert_with(|| Chunk::from_sorted_label(label.clone()))
+                    .checked
+                    .push(g.clone());
+            }
+        }
+        (shared, only)
+    }
+
+    pub fn generate_next_size<L: Labeler<T, G> + ?Sized>(&self, labeler: &L) -> Self {
+        self.generate_next_size_with(labeler, &GenerateOptions::default())
+    }
+
+    /// The next tier, keeping the children with no red K_s and no blue K_t
+    ///
+    /// Runs in a rayon pool of its own with a thread for every worker plus one for
+    /// the task that cleans up while they run. On the global pool a machine with
+    /// fewer cores than that would never finish. Progress goes to a `Progress` bar
+    pub fn generate_next_size_with<L: Labeler<T, G> + ?Sized>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+    ) -> Self {
+        self.generate_next_size_observed(labeler, options, &Progress::new())
     }
 
-    //FIXME: This needs two functions, a filter for generating next size and a labeling function
-    pub fn generate_next_size<L: Sync + Fn(&Graph, &mut Vec<Vec<(u32, u32)>>) -> Vec<T>>(&self, labeler: &L) -> Self {
-        use std::sync::atomic::{AtomicU64, Ordering};
-        use std::sync::{Arc, Mutex};
-        let mut out: Arc<Mutex<Tier<T>>> = Arc::new(Mutex::new(Tier::default()));
-        let mut count = &AtomicU64::new(0);
-        let bar = &ProgressBar::new(self.count_chunks() as u64);
-        let mut clean_count = AtomicU64::new(0);
-        let mut prev_g_count = AtomicU64::new(0);
+    /// `generate_next_size_with`, telling observer what it does instead of
+    /// printing it
+    pub fn generate_next_size_observed<L, O>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+        observer: &O,
+    ) -> Self
+    where
+        L: Labeler<T, G> + ?Sized,
+        O: GenerationObserver + ?Sized,
+    {
+        self.generate_next_size_report(labeler, options, observer).tier
+    }
+
+    /// `generate_next_size_observed` with a report of what each step cost
+    pub fn generate_next_size_report<L, O>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+        observer: &O,
+    ) -> Generation<T, G>
+    where
+        L: Labeler<T, G> + ?Sized,
+        O: GenerationObserver + ?Sized,
+    {
+        self.generate_next_size_budgeted(labeler, options, observer, &Budget::default())
+    }
+
+    /// `generate_next_size_report` that stops taking parent chunks once budget
+    /// is spent. The children found until then are still cleaned and returned
+    pub fn generate_next_size_budgeted<L, O>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+        observer: &O,
+        budget: &Budget,
+    ) -> Generation<T, G>
+    where
+        L: Labeler<T, G> + ?Sized,
+        O: GenerationObserver + ?Sized,
+    {
+        generation_pool(options).install(|| {
+            self.generate_in_pool(labeler, options, observer, budget, &|_: &ChunkLabeling<T>| true)
+        })
+    }
+
+    /// The next tier without keeping it, each cleaned chunk goes to sink
+    ///
+    /// Chunks are split into `passes` buckets by the hash of their label and
+    /// every pass generates the whole next size but keeps only one bucket, so at
+    /// most about 1/passes of the tier is in memory at once, for passes times
+    /// the extension and labeling work. Returns the number of classes. The
+    /// observer sees every pass as a generation of its own
+    pub fn generate_next_size_streamed<L, O, S>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+        observer: &O,
+        passes: usize,
+        sink: &mut S,
+    ) -> usize
+    where
+        L: Labeler<T, G> + ?Sized,
+        O: GenerationObserver + ?Sized,
+        S: ChunkSink<T, G> + ?Sized,
+    {
+        assert!(passes > 0, "streaming needs at least one pass");
+        let pool = generation_pool(options);
+        let mut classes = 0;
+        for pass in 0..passes {
+            let in_pass = |label: &ChunkLabeling<T>| {
+                let mut hasher = DefaultHasher::new();
+                label.hash(&mut hasher);
+                hasher.finish() % passes as u64 == pass as u64
+            };
+            let generation = pool.install(|| {
+                self.generate_in_pool(labeler, options, observer, &Budget::default(), &in_pass)
+            });
+            for (_, chunk) in generation.tier.map.into_iter() {
+                classes += chunk.checked.len();
+                sink.chunk(chunk);
+            }
+        }
+        classes
+    }
+
+    // children whose chunk fails keep are dropped right after labeling
+    fn generate_in_pool<L, O>(
+        &self,
+        labeler: &L,
+        options: &GenerateOptions,
+        observer: &O,
+        budget: &Budget,
+        keep: &(dyn Fn(&ChunkLabeling<T>) -> bool + Sync),
+    ) -> Generation<T, G>
+    where
+        L: Labeler<T, G> + ?Sized,
+        O: GenerationObserver + ?Sized,
+    {
+        use std::sync::atomic::{AtomicU64, AtomicUsize};
+        use std::sync::Mutex;
+        use std::cmp::Reverse;
+        use std::time::Duration;
+        let started = Instant::now();
+        let descendants_count = &AtomicU64::new(0);
+        let rejected_count = &AtomicU64::new(0);
+        let skipped_count = &AtomicU64::new(0);
+        // nanoseconds, summed over the workers
+        let extension_ns = &AtomicU64::new(0);
+        let labeling_ns = &AtomicU64::new(0);
+        let merging_ns = &AtomicU64::new(0);
+        let cleaning_ns = &AtomicU64::new(0);
+        let peak_unchecked = &AtomicUsize::new(0);
+        let completed = &Mutex::new(Vec::new());
+        let cancelled = &AtomicBool::new(false);
+        let out: Arc<Mutex<Tier<T, G>>> = Arc::new(Mutex::new(Tier::default()));
+        let count = &AtomicU64::new(0);
+        let clean_count = AtomicU64::new(0);
+        let prev_g_count = AtomicU64::new(0);
         let cycles = &AtomicU64::new(0);
-        const NUM_THREADS: usize = 4;
-        let working = &AtomicU64::new(NUM_THREADS as u64);
-        let new_num_verts = self.map.iter().next().unwrap().1.checked[0].inner.num_verts() + 1;
-        rayon::scope(|s| {
-            for i in 0..NUM_THREADS {
+        let num_threads = options.threads;
+        let (s, t) = (options.s, options.t);
+        let working = &AtomicU64::new(num_threads as u64);
+        observer.observe(Event::Started {
+            graphs: self.count_graphs().0,
+            chunks: self.count_chunks(),
+        });
+        rayon::scope(|scope| {
+            for i in 0..num_threads {
                 let out = out.clone();
-                s.spawn(move |_| {
+                scope.spawn(move |_| {
                     let mut temp = Tier::default();
-                    let mut label_buffer = vec![Vec::with_capacity(new_num_verts); new_num_verts];
-                    for chunk in self.map.values().skip(i).step_by(NUM_THREADS) {
+                    let (mut extension, mut labeling) = (Duration::default(), Duration::default());
+                    let (mut rejected, mut skipped) = (0, 0);
+                    let mut done = vec![];
+                    for (label, chunk) in self.map.iter().skip(i).step_by(num_threads) {
+                        if budget.is_spent() {
+                            cancelled.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        let mut children = 0;
                         for graph in chunk.checked.iter() {
+                            let start = Instant::now();
                             //This is producing a huge vec that we then iterate over
                             // (2^num_verts) graphs to be precise
                             let descendants = graph.inner.get_next_size();
-                            for descendant in descendants.into_iter() {
-                                if !descendant.has_kns(4, 4) {
-                                    let mut label = labeler(&descendant, &mut label_buffer);
-                                    let mut label_sorted = label.clone();
-                                    label_sorted.sort();
-                                    let label = normalize(&mut label, &label_sorted);
-                                    let label_sorted1 = ChunkLabeling(label_sorted);
-                                    let label_sorted2 = label_sorted1.clone();
-                                    count.fetch_add(1, Ordering::Relaxed);
-
-                                    let out_chunk = temp
-                                        .map
-                                        .entry(label_sorted1)
-                                        .or_insert_with(|| Chunk::from_sorted_label(label_sorted2));
-
-                                    out_chunk.unchecked.push(Some(LabeledGraph {
-                                        inner: descendant,
-                                        labels: GraphLabeling(label),
-                                    }));
+                            let children_of = descendants.len();
+                            descendants_count.fetch_add(children_of as u64, Ordering::Relaxed);
+                            let kept: Vec<G> = descendants
+                                .into_iter()
+                                .filter(|descendant| !descendant.has_kns(s, t))
+                                .collect();
+                            rejected += (children_of - kept.len()) as u64;
+                            let labeled_at = Instant::now();
+                            extension += labeled_at - start;
+                            let kept_labeled: Vec<_> = kept
+                                .into_iter()
+                                .map(|descendant| label_graph(descendant, labeler))
+                                .collect();
+                            labeling += labeled_at.elapsed();
+                            for (label_sorted1, labeled) in kept_labeled.into_iter() {
+                                if !keep(&label_sorted1) {
+                                    skipped += 1;
+                                    continue;
                                 }
+                                let label_sorted2 = label_sorted1.clone();
+                                count.fetch_add(1, Ordering::Relaxed);
+                                children += 1;
+
+                                let out_chunk = temp
+                                    .map
+                                    .entry(label_sorted1)
+                                    .or_insert_with(|| Chunk::from_sorted_label(label_sorted2));
+
+                                out_chunk.unchecked.push(Some(labeled));
                             }
                         }
-                        bar.inc(1);
+                        observer.observe(Event::ChunkProcessed { children });
+                        done.push(label.clone());
                         //after some threshold, dump the temp map into the out tier
                         if temp.count_unchecked().0 > 500_000 {
+                            let start = Instant::now();
                             let mut out = out.lock().unwrap();
                             for mut c in temp.map.into_iter() {
                                 let out_chunk = out
@@ -233,13 +604,17 @@ impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
                                     .or_insert_with(|| Chunk::from_sorted_label(c.0.clone()));
                                 out_chunk.unchecked.append(&mut c.1.unchecked);
                             }
-                            cycles.fetch_add(1, Ordering::Relaxed);
+                            peak_unchecked.fetch_max(out.count_unchecked().0, Ordering::Relaxed);
+                            merging_ns.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                            let cycle = cycles.fetch_add(1, Ordering::Relaxed) + 1;
+                            observer.observe(Event::Flushed { cycle });
                             temp = Tier::default();
                         }
                     }
 
                     //FIXME: deduplicate from above code
                     //This happens at the end to make sure all graphs get moved over
+                    let start = Instant::now();
                     let mut out = out.lock().unwrap();
                     for mut c in temp.map.into_iter() {
                         let out_chunk = out
@@ -248,11 +623,18 @@ impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
                             .or_insert_with(|| Chunk::from_sorted_label(c.0.clone()));
                         out_chunk.unchecked.append(&mut c.1.unchecked);
                     }
+                    peak_unchecked.fetch_max(out.count_unchecked().0, Ordering::Relaxed);
+                    merging_ns.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                    extension_ns.fetch_add(extension.as_nanos() as u64, Ordering::Relaxed);
+                    labeling_ns.fetch_add(labeling.as_nanos() as u64, Ordering::Relaxed);
+                    rejected_count.fetch_add(rejected, Ordering::Relaxed);
+                    skipped_count.fetch_add(skipped, Ordering::Relaxed);
+                    completed.lock().unwrap().append(&mut done);
                     working.fetch_sub(1, Ordering::Relaxed);
                 });
             }
             let out = out.clone();
-            s.spawn(move |_| {
+            scope.spawn(move |_| {
                 let mut last = cycles.load(Ordering::Relaxed);
                 while working.load(Ordering::Relaxed) > 0 {
                     std::thread::sleep(std::time::Duration::from_millis(100));
@@ -262,6 +644,7 @@ impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
                         let tp = rayon::ThreadPoolBuilder::default().build().unwrap();
                         tp.install(|| {
                             let mut out = out.lock().unwrap();
+                            let start = Instant::now();
                             out.map.values_mut().par_bridge().for_each(|chunk| {
                                 chunk.clean_isos();
                             });
@@ -269,58 +652,126 @@ impl<T: Hash + Debug + Eq + Clone + Ord + Send + Sync> Tier<T> {
                             let cs = out.count_chunks();
                             let gs = out.count_graphs();
                             clean_count.fetch_add(1, Ordering::Relaxed);
-                            println!(
-                                "{} graphs and {} chunks\n+{} graphs, cleaning {}\n",
-                                gs.0,
-                                cs,
-                                gs.0 as u64 - prev_g_count.load(Ordering::Relaxed),
-                                clean_count.load(Ordering::Relaxed),
-                            );
+                            observer.observe(Event::Cleaned {
+                                pass: clean_count.load(Ordering::Relaxed),
+                                graphs: gs.0,
+                                chunks: cs,
+                                added: (gs.0 as u64 - prev_g_count.load(Ordering::Relaxed)) as usize,
+                            });
                             prev_g_count.store(gs.0 as u64, Ordering::Relaxed);
+                            cleaning_ns.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
                         });
                     }
                 }
             });
         });
-        bar.finish();
-        let mut out = Arc::try_unwrap(out).unwrap().into_inner().unwrap();
+        let mut out = match Arc::try_unwrap(out) {
+            Ok(out) => out.into_inner().unwrap(),
+            Err(_) => unreachable!("the scope has joined every worker"),
+        };
         let before = out.count_chunks();
-        println!("final cleanup");
+        let completed = completed.lock().unwrap().split_off(0);
+        let cancelled = cancelled.load(Ordering::Relaxed);
+        if cancelled {
+            observer.observe(Event::Cancelled {
+                completed: completed.len(),
+                chunks: self.count_chunks(),
+            });
+        }
+        peak_unchecked.fetch_max(out.count_unchecked().0, Ordering::Relaxed);
+        observer.observe(Event::FinalCleanup { chunks: before });
+        let cleanup = Instant::now();
+        let expensive = Mutex::new(Vec::new());
         out.map.values_mut().par_bridge().for_each(|chunk| {
-            if chunk.comp > 10_000_000 && chunk.unchecked.len() > 0 {
-                println!(
-                    "cleaning {}, {}, comp {}",
-                    chunk.checked.len(),
-                    chunk.unchecked.len(),
-                    chunk.comp
-                );
+            if chunk.comp > 10_000_000 && !chunk.unchecked.is_empty() {
+                observer.observe(Event::SlowClean {
+                    checked: chunk.checked.len(),
+                    unchecked: chunk.unchecked.len(),
+                    comp: chunk.comp,
+                });
             }
 
+            let start = Instant::now();
+            let candidates = chunk.checked.len() + chunk.unchecked.len();
             chunk.clean_isos();
             chunk.trim();
+            let cost = ChunkCost {
+                comp: chunk.comp,
+                candidates,
+                graphs: chunk.checked.len(),
+                cleaning: start.elapsed(),
+            };
+            let mut expensive = expensive.lock().unwrap();
+            expensive.push(cost);
+            if expensive.len() >= 2 * EXPENSIVE_CHUNKS {
+                expensive.sort_by_key(|c| Reverse(c.comp));
+                expensive.truncate(EXPENSIVE_CHUNKS);
+            }
         });
+        cleaning_ns.fetch_add(cleanup.elapsed().as_nanos() as u64, Ordering::Relaxed);
         assert!(out.count_chunks() == before);
-        out
-    }
-}
+        observer.observe(Event::Finished {
+            graphs: out.count_graphs().0,
+            chunks: before,
+        });
 
-fn compute_complexity<T: Eq + Hash>(labeling_sorted: &ChunkLabeling<T>) -> u64 {
-    let labeling_sorted = &labeling_sorted.0;
-    let mut comp = 1u64;
-    let mut current = &labeling_sorted[0];
-    let mut cur_streak = 1;
-    for i in 1..labeling_sorted.len() {
-        if labeling_sorted[i] == *current {
-            cur_streak += 1;
-        } else {
-            current = &labeling_sorted[i];
-            comp *= util::factorial(&cur_streak);
-            cur_streak = 1;
+        let mut expensive_chunks = expensive.into_inner().unwrap();
+        expensive_chunks.sort_by_key(|c| Reverse(c.comp));
+        expensive_chunks.truncate(EXPENSIVE_CHUNKS);
+        let kept = count.load(Ordering::Relaxed);
+        let nanos = |ns: &AtomicU64| Duration::from_nanos(ns.load(Ordering::Relaxed));
+        let report = GenerationReport {
+            descendants: descendants_count.load(Ordering::Relaxed),
+            rejected: rejected_count.load(Ordering::Relaxed),
+            skipped: skipped_count.load(Ordering::Relaxed),
+            duplicates: kept - out.count_graphs().0 as u64,
+            extension: nanos(extension_ns),
+            labeling: nanos(labeling_ns),
+            merging: nanos(merging_ns),
+            cleaning: nanos(cleaning_ns),
+            total: started.elapsed(),
+            peak_unchecked: peak_unchecked.load(Ordering::Relaxed),
+            expensive_chunks,
+        };
+        Generation {
+            tier: out,
+            report,
+            completed,
+            cancelled,
         }
     }
-    comp *= util::factorial(&cur_streak);
-    //println!("{} {:?}", comp, labeling_sorted);
-    comp
+}
+
+// a thread for every worker and one for the task cleaning up while they run
+fn generation_pool(options: &GenerateOptions) -> rayon::ThreadPool {
+    assert!(options.threads > 0, "generation needs at least one worker");
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads + 1)
+        .build()
+        .unwrap()
+}
+
+fn label_graph<T, G, L>(g: G, labeler: &L) -> (ChunkLabeling<T>, LabeledGraph<G>)
+where
+    T: Hash + Ord + Clone,
+    L: Labeler<T, G> + ?Sized,
+{
+    let label = labeler.label(&g);
+    with_labels(g, &label)
+}
+
+// the chunk and labeled graph of g for the vertex labels `label`
+fn with_labels<T: Hash + Ord + Clone, G>(g: G, label: &[T]) -> (ChunkLabeling<T>, LabeledGraph<G>) {
+    let mut label_sorted = label.to_vec();
+    label_sorted.sort();
+    let label = normalize(label, &label_sorted);
+    (
+        ChunkLabeling(label_sorted),
+        LabeledGraph {
+            inner: g,
+            labels: GraphLabeling(label),
+        },
+    )
 }
 
 //no element in either should be <= length of the lists
@@ -345,21 +796,21 @@ fn collapse_verts(v: &Vec<Vec<usize>>, out: &mut Vec<usize>) {
     }
 }
 
-fn compare(
-    g: &LabeledGraph,
-    h: &LabeledGraph,
+fn compare<G: GraphLike>(
+    g: &LabeledGraph<G>,
+    h: &LabeledGraph<G>,
     n: usize, //how many verts to compare
     verts_g: &mut Vec<Vec<usize>>,
     collapsed_verts_g: &mut Vec<usize>,
     collapsed_verts_h: &Vec<usize>,
 ) -> bool {
     collapse_verts(&verts_g, collapsed_verts_g);
-    for i in 0..(n - 1) {
+    for i in 0..n {
         for j in i + 1..n {
             if g.inner
-                .get_edge(collapsed_verts_g[i as usize], collapsed_verts_g[j as usize])
+                .edge_color(collapsed_verts_g[i], collapsed_verts_g[j])
                 != h.inner
-                    .get_edge(collapsed_verts_h[i as usize], collapsed_verts_h[j as usize])
+                    .edge_color(collapsed_verts_h[i], collapsed_verts_h[j])
             {
                 return false;
             }
@@ -368,7 +819,7 @@ fn compare(
     return true;
 }
 
-fn permute(
+fn permute<G: GraphLike>(
     depth_to_now: usize,
     sub_depth: usize,
     depth: usize,
@@ -376,8 +827,8 @@ fn permute(
     verts_g: &mut Vec<Vec<usize>>,
     collapsed_verts_g: &mut Vec<usize>,
     collapsed_verts_h: &Vec<usize>,
-    g: &LabeledGraph,
-    h: &LabeledGraph,
+    g: &LabeledGraph<G>,
+    h: &LabeledGraph<G>,
 ) -> bool {
     if sub_depth == verts_g[depth].len() {
         return rec_iso_check(
@@ -432,14 +883,14 @@ fn permute(
     return false;
 }
 
-pub fn rec_iso_check(
+pub fn rec_iso_check<G: GraphLike>(
     depth: usize,
     orig_verts_g: &Vec<Vec<usize>>,
     verts_g: &mut Vec<Vec<usize>>,
     collapsed_verts_h: &Vec<usize>,
     collapsed_verts_g: &mut Vec<usize>,
-    g: &LabeledGraph,
-    h: &LabeledGraph,
+    g: &LabeledGraph<G>,
+    h: &LabeledGraph<G>,
 ) -> bool {
     if depth >= g.inner.num_verts() {
         let res = compare(
@@ -475,7 +926,7 @@ pub fn rec_iso_check(
 }
 
 // probably have a different function for when all vertices are indistinguishable
-pub fn is_color_iso(g: &LabeledGraph, h: &LabeledGraph) -> bool {
+pub fn is_color_iso<G: GraphLike>(g: &LabeledGraph<G>, h: &LabeledGraph<G>) -> bool {
     //println!("starting comparison ==============================================");
 
     //TODO normalize vertex labelings
@@ -519,3 +970,226 @@ pub fn is_color_iso(g: &LabeledGraph, h: &LabeledGraph) -> bool {
         h,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_like::GraphMut;
+    use crate::labeler::Pipeline;
+    use crate::test_util::{tier_up_to, Rng};
+    use permutohedron::Heap;
+
+    // some label preserving map of the vertices of h onto those of g keeps
+    // every edge color, trying all n! of them
+    fn brute_iso(g: &LabeledGraph, h: &LabeledGraph) -> bool {
+        let n = g.inner.num_verts();
+        let mut verts: Vec<usize> = (0..n).collect();
+        Heap::new(&mut verts).any(|p| {
+            (0..n).all(|i| g.labels.0[p[i]] == h.labels.0[i])
+                && (0..n).all(|i| {
+                    (i + 1..n).all(|j| g.inner.edge_color(p[i], p[j]) == h.inner.edge_color(i, j))
+                })
+        })
+    }
+
+    // every stage on its own and a couple of pipelines
+    fn labelers() -> Vec<Pipeline> {
+        let specs = [
+            "degree", "polygons", "k3", "k4", "k5", "graphlets", "walks", "wl1", "wl2", "conv",
+            "degree:both,polygons:blue,conv:2:both",
+        ];
+        let mut labelers: Vec<Pipeline> = specs.iter().map(|s| s.parse().unwrap()).collect();
+        labelers.push(Pipeline::standard());
+        labelers
+    }
+
+    #[test]
+    fn labels_survive_relabeling() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let labelers = labelers();
+        for _ in 0..150 {
+            let n = 1 + rng.below(11);
+            let g = rng.graph(n);
+            let perm = rng.permutation(n);
+            let h = g.induced_subgraph(&perm);
+            for labeler in labelers.iter() {
+                let (chunk_g, labeled_g) = label_graph(g.clone(), labeler);
+                let (chunk_h, labeled_h) = label_graph(h.clone(), labeler);
+                assert_eq!(chunk_g, chunk_h, "{:?} under {:?}", g, perm);
+                // vertex i of h is vertex perm[i] of g
+                for (i, &v) in perm.iter().enumerate() {
+                    assert_eq!(labeled_h.labels.0[i], labeled_g.labels.0[v]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn iso_matches_brute_force() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let labeler: Pipeline = "degree".parse().unwrap();
+        for n in 1..=8 {
+            let rounds = if n < 8 { 60 } else { 15 };
+            let mut chunks: HashMap<ChunkLabeling<u32>, Vec<LabeledGraph>> = HashMap::new();
+            for _ in 0..rounds {
+                let g = rng.graph(n);
+                let h = g.induced_subgraph(&rng.permutation(n));
+                // turning red ab, cd and blue ac, bd the other way round keeps
+                // every degree, so f shares the chunk of g but is rarely a copy
+                let mut f = h.clone();
+                for _ in 0..20 {
+                    let v = rng.permutation(n);
+                    if n >= 4
+                        && f.get_edge(v[0], v[1])
+                        && f.get_edge(v[2], v[3])
+                        && !f.get_edge(v[0], v[2])
+                        && !f.get_edge(v[1], v[3])
+                    {
+                        for &(a, b) in [(0, 1), (2, 3), (0, 2), (1, 3)].iter() {
+                            f.flip_edge(v[a], v[b]);
+                        }
+                        break;
+                    }
+                }
+                for graph in [g, h, f].iter() {
+                    let (chunk, labeled) = label_graph(graph.clone(), &labeler);
+                    chunks.entry(chunk).or_default().push(labeled);
+                }
+            }
+            let mut seen = [0, 0];
+            for graphs in chunks.values() {
+                for (i, g) in graphs.iter().enumerate() {
+                    for h in graphs.iter().skip(i).take(6) {
+                        let iso = brute_iso(g, h);
+                        assert_eq!(is_color_iso(g, h), iso, "{:?} and {:?}", g, h);
+                        seen[iso as usize] += 1;
+                    }
+                }
+            }
+            assert!(seen[1] >= rounds && (n < 5 || seen[0] > 0));
+        }
+    }
+
+    #[test]
+    fn pass_reports() {
+        use crate::observer::Silent;
+        let labeler = Pipeline::standard();
+        let options = GenerateOptions::default();
+        let tier = tier_up_to(5, &labeler);
+        let whole = tier.generate_next_size_report(&labeler, &options, &Silent).report;
+
+        // two passes split the kept children between them, neither counts the
+        // other's as rejected
+        let mut kept = 0;
+        for pass in 0..2 {
+            let in_pass = |label: &ChunkLabeling<u32>| {
+                let mut hasher = DefaultHasher::new();
+                label.hash(&mut hasher);
+                hasher.finish() % 2 == pass
+            };
+            let budget = Budget::default();
+            let report = generation_pool(&options)
+                .install(|| tier.generate_in_pool(&labeler, &options, &Silent, &budget, &in_pass))
+                .report;
+            assert_eq!(report.descendants, whole.descendants);
+            assert_eq!(report.rejected, whole.rejected);
+            assert!(report.skipped > 0);
+            kept += report.kept();
+        }
+        assert_eq!(kept, whole.kept());
+    }
+
+    #[test]
+    fn normalize_indexes_sorted() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        let mut cases: Vec<Vec<u32>> = vec![vec![], vec![7], vec![3; 9]];
+        for _ in 0..200 {
+            let n = rng.below(13);
+            let range = 1 + rng.below(n + 1) as u64;
+            cases.push((0..n).map(|_| (rng.next() % range) as u32).collect());
+        }
+        for unsorted in cases {
+            let mut sorted = unsorted.clone();
+            sorted.sort();
+            let out = normalize(&unsorted, &sorted);
+            assert_eq!(out.len(), unsorted.len());
+            for (i, &o) in out.iter().enumerate() {
+                let o = o as usize;
+                assert_eq!(sorted[o], unsorted[i]);
+                // the last of a run of equal labels, so equal labels share an index
+                assert!(o + 1 == sorted.len() || sorted[o + 1] != sorted[o]);
+            }
+        }
+        assert_eq!(normalize(&[5, 5, 5, 5], &[5, 5, 5, 5]), vec![3; 4]);
+        assert_eq!(normalize(&[2, 0, 1], &[0, 1, 2]), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn collapse_verts_flattens() {
+        let mut out = vec![9, 9, 9, 9, 9, 9, 9];
+        collapse_verts(&vec![vec![], vec![4], vec![], vec![0, 3, 1], vec![2]], &mut out);
+        assert_eq!(out, vec![4, 0, 3, 1, 2]);
+        collapse_verts(&vec![vec![]; 3], &mut out);
+        assert!(out.is_empty());
+        collapse_verts(&vec![], &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn iso_edge_cases() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for n in 1..=7 {
+            for _ in 0..20 {
+                let g = rng.graph(n);
+                let perm = rng.permutation(n);
+                let h = g.induced_subgraph(&perm);
+                let mut f = h.clone();
+                if n > 1 {
+                    f.flip_edge(0, 1 + rng.below(n - 1));
+                }
+
+                // all labels equal, a single partition holding every vertex
+                let same = vec![0u32; n];
+                let (_, lg) = with_labels(g.clone(), &same);
+                let (_, lh) = with_labels(h.clone(), &same);
+                let (_, lf) = with_labels(f.clone(), &same);
+                assert!(is_color_iso(&lg, &lh));
+                assert_eq!(is_color_iso(&lg, &lf), brute_iso(&lg, &lf));
+
+                // all labels distinct, only the map that keeps them is tried
+                let distinct: Vec<u32> = (0..n as u32).collect();
+                let moved: Vec<u32> = perm.iter().map(|&v| distinct[v]).collect();
+                let (_, lg) = with_labels(g.clone(), &distinct);
+                let (_, lh) = with_labels(h.clone(), &moved);
+                assert!(is_color_iso(&lg, &lh));
+                let (_, lf) = with_labels(f.clone(), &moved);
+                assert!(n == 1 || !is_color_iso(&lg, &lf));
+                let (_, lh) = with_labels(h.clone(), &distinct);
+                assert_eq!(is_color_iso(&lg, &lh), brute_iso(&lg, &lh));
+            }
+        }
+
+        // with every partition a singleton, checking from the last depth only
+        // compares the fixed orderings
+        let g = rng.graph(6);
+        let singletons: Vec<Vec<usize>> = (0..6).map(|v| vec![v]).collect();
+        let (_, lg) = with_labels(g.clone(), &[0u32, 1, 2, 3, 4, 5]);
+        let same: Vec<usize> = (0..6).collect();
+        let mut scratch = vec![];
+        let mut flipped = g.clone();
+        flipped.flip_edge(2, 4);
+        let (_, lf) = with_labels(flipped, &[0u32, 1, 2, 3, 4, 5]);
+        for depth in 0..=6 {
+            let mut verts = singletons.clone();
+            assert!(rec_iso_check(depth, &singletons, &mut verts, &same, &mut scratch, &lg, &lg));
+            let mut verts = singletons.clone();
+            assert!(!rec_iso_check(depth, &singletons, &mut verts, &same, &mut scratch, &lg, &lf));
+        }
+
+        // a single vertex and the empty graph
+        let (_, one) = with_labels(Graph::empty(1), &[0u32]);
+        assert!(is_color_iso(&one, &one));
+        let (_, none) = with_labels(Graph::empty(0), &[] as &[u32]);
+        assert!(is_color_iso(&none, &none));
+    }
+}
@@ -0,0 +1,56 @@
+// Fixtures shared by the unit tests of several modules
+
+use crate::graph::Graph;
+use crate::graph_like::GraphMut;
+use crate::labeler::Labeler;
+use crate::structures::Tier;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// xorshift, enough to shake out labelings and backends without pulling in a crate
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Every edge red or blue with even odds
+    pub fn graph(&mut self, n: usize) -> Graph {
+        let mut g = Graph::empty(n);
+        for i in 0..n {
+            for j in i + 1..n {
+                g.set_edge(i, j, self.next() & 1 == 1);
+            }
+        }
+        g
+    }
+
+    pub fn permutation(&mut self, n: usize) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            perm.swap(i, self.below(i + 1));
+        }
+        perm
+    }
+}
+
+/// Every graph on up to n vertices with no Ramsey bounds, the tier of size n
+pub fn tier_up_to<T, L>(n: usize, labeler: &L) -> Tier<T>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+    L: Labeler<T> + ?Sized,
+{
+    let mut tier = Tier::from_graph(Graph::new(1), labeler);
+    for _ in 2..=n {
+        tier = tier.generate_next_size(labeler);
+    }
+    tier
+}
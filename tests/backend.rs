@@ -0,0 +1,217 @@
+// Behaviour every Graph backend has to share, run with each of
+//   cargo test --features bv_graph
+//   cargo test --no-default-features --features bb_graph
+//   cargo test --no-default-features --features vec_graph
+
+extern crate graph_lib;
+
+use graph_lib::error::Error;
+use graph_lib::graph::Graph;
+use graph_lib::graph_like::{GraphLike, GraphMut};
+use graph_lib::labeler::{Labeler, Pipeline};
+
+fn edges(g: &Graph) -> Vec<bool> {
+    let n = g.num_verts();
+    let mut out = vec![];
+    for m in 0..n {
+        for k in 0..m {
+            out.push(g.get_edge(k, m));
+        }
+    }
+    out
+}
+
+#[test]
+fn new_is_all_blue() {
+    let g = Graph::new(5);
+    assert_eq!(g.num_verts(), 5);
+    assert!(edges(&g).iter().all(|e| !e));
+}
+
+#[test]
+fn next_size() {
+    let g = Graph::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+    let children = g.get_next_size();
+    assert_eq!(children.len(), 8);
+    for (mask, child) in children.iter().enumerate() {
+        assert_eq!(child.num_verts(), 4);
+        assert_eq!(&edges(child)[..3], &edges(&g)[..]);
+        for v in 0..3 {
+            assert_eq!(child.get_edge(v, 3), mask & (1 << v) != 0);
+            assert_eq!(child.get_edge(3, v), mask & (1 << v) != 0);
+        }
+    }
+}
+
+#[test]
+fn encoding() {
+    // edges in order 01, 02, 12, 03, 13, 23, 04, ..., first edge in the high bit
+    let g = Graph::from_str("09PT09PQ", 10).unwrap();
+    assert_eq!(
+        &edges(&g)[..8],
+        &[true, true, false, true, false, false, true, true]
+    );
+    assert_eq!(g.to_string(), "09PT09PQ");
+
+    let edge_list = [(0, 1), (0, 2), (1, 2), (2, 3), (1, 4), (3, 4), (1, 5), (2, 5), (4, 5)];
+    let g = Graph::from_edges(6, &edge_list).unwrap();
+    let mut s = String::from("x;");
+    g.to_string_append(&mut s);
+    assert_eq!(s, format!("x;{}", g));
+    let des = Graph::from_str(&g.to_string(), 6).unwrap();
+    assert_eq!(edges(&des), edges(&g));
+}
+
+#[test]
+fn invariants() {
+    // a red triangle 0 1 2 with a pendant vertex 3 on 2
+    let g = Graph::from_edges(4, &[(0, 1), (0, 2), (1, 2), (2, 3)]).unwrap();
+    assert_eq!(g.label_degrees(true), vec![2, 2, 3, 1]);
+    assert_eq!(g.label_degrees(false), vec![1, 1, 0, 2]);
+    assert_eq!(g.label_k3s(true), vec![1, 1, 1, 0]);
+    assert_eq!(g.label_k3s(false), vec![0, 0, 0, 0]);
+    assert_eq!(g.label_k4s(true), vec![0, 0, 0, 0]);
+    assert!(g.has_k3(true));
+    assert!(!g.has_k3(false));
+    assert!(!g.has_kns(4, 4));
+    let k4 = Graph::from_edges(4, &[(0, 1), (0, 2), (1, 2), (0, 3), (1, 3), (2, 3)]).unwrap();
+    assert!(k4.has_kns(4, 4));
+    assert!(Graph::new(4).has_kns(4, 4));
+
+    let polygons = g.label_polygons(true);
+    assert_eq!(polygons[0], polygons[1]);
+    assert_ne!(polygons[0], polygons[2]);
+
+    let labels = Pipeline::standard().label(&g);
+    assert_eq!(labels[0], labels[1]);
+    assert_eq!(
+        labels,
+        Pipeline::standard().label(&Graph::from_str(&g.to_string(), 4).unwrap())
+    );
+}
+
+#[test]
+fn editing() {
+    let mut g = Graph::new(0);
+    assert_eq!(g.num_verts(), 0);
+    g.add_vertex(0);
+    g.add_vertex(0b1);
+    g.add_vertex(0b10);
+    assert_eq!(edges(&g), edges(&Graph::from_edges(3, &[(0, 1), (1, 2)]).unwrap()));
+
+    g.set_edge(2, 0, true);
+    assert!(g.get_edge(0, 2));
+    g.flip_edge(0, 1);
+    assert_eq!(edges(&g), vec![false, true, true]);
+    g.set_edge(0, 2, false);
+    assert_eq!(edges(&g), vec![false, false, true]);
+
+    // removing a vertex keeps the edges among the others, in order
+    let mut h = Graph::from_edges(5, &[(0, 1), (0, 2), (1, 2), (2, 3), (1, 4), (3, 4)]).unwrap();
+    h.remove_vertex(1);
+    let path = Graph::from_edges(4, &[(0, 1), (1, 2), (2, 3)]).unwrap();
+    assert_eq!(edges(&h), edges(&path));
+    h.remove_vertex(3);
+    assert_eq!(edges(&h), edges(&Graph::from_edges(3, &[(0, 1), (1, 2)]).unwrap()));
+}
+
+#[test]
+fn operations() {
+    // the path 0 - 1 - 2
+    let path = Graph::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+    let flipped = path.complement();
+    assert_eq!(edges(&flipped), vec![false, true, false]);
+    assert_eq!(edges(&flipped.complement()), edges(&path));
+
+    let sub = path.induced_subgraph(&[2, 0, 1]);
+    assert_eq!(edges(&sub), vec![false, true, true]);
+    assert_eq!(sub.label_degrees(true), vec![1, 1, 2]);
+
+    let edge = Graph::from_edges(2, &[(0, 1)]).unwrap();
+    let union = path.disjoint_union(&edge);
+    assert_eq!(union.num_verts(), 5);
+    assert_eq!(union.label_degrees(true), vec![1, 2, 1, 1, 1]);
+    assert!(!union.get_edge(2, 3));
+
+    let join = path.join(&edge);
+    assert_eq!(join.label_degrees(true), vec![3, 4, 3, 4, 4]);
+    assert!(join.has_k4(true));
+    assert_eq!(edges(&join.induced_subgraph(&[0, 1, 2])), edges(&path));
+}
+
+#[test]
+fn constructors() {
+    // a red 4-cycle 0 1 2 3 with the chord 0 2
+    let g = Graph::from_edges(4, &[(0, 1), (2, 1), (2, 3), (3, 0), (0, 2), (1, 0)]).unwrap();
+    assert_eq!(edges(&g), vec![true, true, true, true, false, true]);
+    assert_eq!(g.to_edges(), vec![(0, 1), (0, 2), (1, 2), (0, 3), (2, 3)]);
+    assert_eq!(g.to_adjacency_rows(), vec![0b1110, 0b0101, 0b1011, 0b0101]);
+
+    let matrix = g.to_adjacency_matrix();
+    assert_eq!(matrix[1], vec![true, false, true, false]);
+    let from_matrix = Graph::from_adjacency_matrix(&matrix).unwrap();
+    assert_eq!(edges(&from_matrix), edges(&g));
+    let from_rows = Graph::from_adjacency_rows(&g.to_adjacency_rows()).unwrap();
+    assert_eq!(edges(&from_rows), edges(&g));
+    assert_eq!(Graph::from_edges(0, &[]).unwrap().num_verts(), 0);
+}
+
+#[test]
+fn constructor_errors() {
+    assert_eq!(
+        Graph::from_edges(3, &[(0, 1), (1, 1)]).unwrap_err(),
+        Error::SelfLoop(1)
+    );
+    assert_eq!(
+        Graph::from_edges(3, &[(0, 3)]).unwrap_err(),
+        Error::VertexOutOfRange {
+            vertex: 3,
+            num_verts: 3
+        }
+    );
+
+    let mut matrix = vec![vec![false; 3]; 3];
+    matrix[0][2] = true;
+    assert_eq!(
+        Graph::from_adjacency_matrix(&matrix).unwrap_err(),
+        Error::NotSymmetric(0, 2)
+    );
+    matrix[2][0] = true;
+    matrix[1][1] = true;
+    assert_eq!(
+        Graph::from_adjacency_matrix(&matrix).unwrap_err(),
+        Error::SelfLoop(1)
+    );
+    matrix[1].pop();
+    assert_eq!(
+        Graph::from_adjacency_matrix(&matrix).unwrap_err(),
+        Error::NotSquare {
+            row: 1,
+            len: 2,
+            num_verts: 3
+        }
+    );
+
+    assert_eq!(
+        Graph::from_adjacency_rows(&[0b10, 0b0]).unwrap_err(),
+        Error::NotSymmetric(0, 1)
+    );
+    assert_eq!(
+        Graph::from_adjacency_rows(&[0b1, 0b0]).unwrap_err(),
+        Error::SelfLoop(0)
+    );
+    assert_eq!(
+        Graph::from_adjacency_rows(&[0b100, 0b0]).unwrap_err(),
+        Error::VertexOutOfRange {
+            vertex: 2,
+            num_verts: 2
+        }
+    );
+    assert_eq!(
+        Graph::from_adjacency_rows(&[0; 65]).unwrap_err(),
+        Error::TooManyVertices {
+            num_verts: 65,
+            max: 64
+        }
+    );
+}
@@ -0,0 +1,20 @@
+// Fixtures shared by the integration tests
+
+use graph_lib::graph::Graph;
+use graph_lib::labeler::Labeler;
+use graph_lib::structures::Tier;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Every graph on up to n vertices with no Ramsey bounds, the tier of size n
+pub fn tier_up_to<T, L>(n: usize, labeler: &L) -> Tier<T>
+where
+    T: Hash + Debug + Eq + Clone + Ord + Send + Sync,
+    L: Labeler<T> + ?Sized,
+{
+    let mut tier = Tier::from_graph(Graph::new(1), labeler);
+    for _ in 2..=n {
+        tier = tier.generate_next_size(labeler);
+    }
+    tier
+}
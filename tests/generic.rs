@@ -0,0 +1,235 @@
+// Tiers over the crate's Graph and over graph types defined elsewhere
+
+extern crate graph_lib;
+
+mod common;
+
+use graph_lib::constructions;
+use graph_lib::graph::Graph;
+use graph_lib::graph_like::{Extendable, GraphLike, GraphMut};
+use graph_lib::labeler::Pipeline;
+use graph_lib::observer::{Event, GenerationObserver, Silent};
+use graph_lib::structures::{Budget, CancelToken, GenerateOptions, Tier};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// complete graph with every edge colored 0..colors, color 1 counts as red
+#[derive(Clone, Debug)]
+struct Colored {
+    colors: u8,
+    matrix: Vec<Vec<u8>>,
+}
+
+impl Colored {
+    fn new(colors: u8) -> Self {
+        Colored {
+            colors,
+            matrix: vec![vec![0]],
+        }
+    }
+}
+
+impl GraphLike for Colored {
+    fn get_edge(&self, n: usize, m: usize) -> bool {
+        self.matrix[n][m] == 1
+    }
+
+    fn num_verts(&self) -> usize {
+        self.matrix.len()
+    }
+
+    fn edge_color(&self, n: usize, m: usize) -> u8 {
+        self.matrix[n][m]
+    }
+}
+
+impl Extendable for Colored {
+    fn get_next_size(&self) -> Vec<Self> {
+        let n = self.num_verts();
+        let mut out = vec![self.clone()];
+        for v in 0..n {
+            let mut next = vec![];
+            for g in out {
+                for c in 0..self.colors {
+                    let mut h = g.clone();
+                    h.matrix[v].push(c);
+                    next.push(h);
+                }
+            }
+            out = next;
+        }
+        for g in out.iter_mut() {
+            let row: Vec<u8> = (0..n).map(|v| g.matrix[v][n]).chain(Some(0)).collect();
+            g.matrix.push(row);
+        }
+        out
+    }
+}
+
+#[test]
+fn two_colors_match_known_counts() {
+    let labeler = Pipeline::standard();
+    let mut tier = Tier::from_graph(Colored::new(2), &labeler);
+    for &expected in [2, 4, 9, 24, 84].iter() {
+        tier = tier.generate_next_size(&labeler);
+        assert_eq!(tier.count_graphs().0, expected);
+    }
+}
+
+#[test]
+fn three_colors() {
+    // the labeler only sees color 1, the isomorphism check sees all three
+    let labeler = Pipeline::standard();
+    let mut tier = Tier::from_graph(Colored::new(3), &labeler);
+    tier = tier.generate_next_size(&labeler);
+    assert_eq!(tier.count_graphs().0, 3);
+    // a triangle's coloring is a multiset of three colors
+    tier = tier.generate_next_size(&labeler);
+    assert_eq!(tier.count_graphs().0, 10);
+}
+
+#[test]
+fn find() {
+    let labeler = Pipeline::standard();
+    let tier = common::tier_up_to(5, &labeler);
+
+    // the pentagon, relabeled so it is not the copy the tier holds
+    let pentagon = Graph::from_edges(5, &[(0, 2), (2, 4), (4, 1), (1, 3), (3, 0)]).unwrap();
+    let (label, i) = tier.find(&pentagon, &labeler).unwrap();
+    let found = &tier.map[&label].checked[i].inner;
+    assert_eq!(found.label_degrees(true), vec![2; 5]);
+    assert!(!found.has_k3(true) && !found.has_k3(false));
+    assert!(tier.find(&constructions::circulant(5, &[2]).unwrap(), &labeler).is_some());
+
+    // every red K4 was filtered out during generation
+    let k4 = Graph::from_edges(5, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]).unwrap();
+    assert!(tier.find(&k4, &labeler).is_none());
+}
+
+#[test]
+fn set_operations() {
+    let standard = Pipeline::standard();
+    let wl1: Pipeline = "wl1".parse().unwrap();
+    let tier = common::tier_up_to(5, &standard);
+    // the same classes under different chunk labels
+    let other = tier.relabel(&wl1);
+    assert_eq!(tier.diff(&other, &standard).count_graphs().0, 0);
+    assert_eq!(other.diff(&tier, &wl1).count_graphs().0, 0);
+    assert_eq!(tier.intersection(&other, &standard).count_graphs().0, 24);
+
+    // drop the graphs with a red triangle from one side
+    let mut partial: Tier<u32> = Tier::default();
+    for g in tier.graphs().filter(|g| !g.has_k3(true)) {
+        partial.insert_checked(g.clone(), &wl1);
+    }
+    let missing = tier.diff(&partial, &standard);
+    assert_eq!(missing.count_graphs().0, 24 - partial.count_graphs().0);
+    assert!(missing.graphs().all(|g| g.has_k3(true)));
+    assert_eq!(partial.diff(&tier, &wl1).count_graphs().0, 0);
+    assert_eq!(
+        tier.intersection(&partial, &standard).count_graphs().0,
+        partial.count_graphs().0
+    );
+
+    let merged = partial.merge(&tier, &wl1);
+    assert_eq!(merged.count_graphs().0, 24);
+    assert_eq!(merged.diff(&tier, &wl1).count_graphs().0, 0);
+}
+
+#[test]
+fn extend_curated_set() {
+    let labeler = Pipeline::standard();
+    let tier = common::tier_up_to(5, &labeler);
+
+    // every graph twice, the second copy with its vertices reversed
+    let reversed = tier.graphs().map(|g| {
+        let order: Vec<usize> = (0..5).rev().collect();
+        g.induced_subgraph(&order)
+    });
+    let curated = tier.graphs().cloned().chain(reversed).collect::<Vec<_>>();
+    let mut start = Tier::from_graphs(curated, &labeler);
+    assert_eq!(start.count_graphs().0, 24);
+    assert!(!start.insert_unique(tier.graphs().next().unwrap().clone(), &labeler));
+    assert_eq!(start.generate_next_size(&labeler).count_graphs().0, 84);
+
+    // a partial start only gives the children of what it has
+    let pentagon = Graph::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]).unwrap();
+    let next = Tier::from_graphs(vec![pentagon], &labeler).generate_next_size(&labeler);
+    assert!(next.count_graphs().0 > 0 && next.count_graphs().0 < 84);
+    assert_eq!(next.diff(&tier.generate_next_size(&labeler), &labeler).count_graphs().0, 0);
+}
+
+#[test]
+fn ramsey_options() {
+    // one worker has to be enough, whatever the size of the global pool
+    let options = GenerateOptions {
+        s: 3,
+        t: 3,
+        threads: 1,
+    };
+    let labeler = Pipeline::standard();
+    let mut tier = Tier::from_graph(Graph::new(1), &labeler);
+    let mut counts = vec![];
+    for _ in 2..=6 {
+        tier = tier.generate_next_size_with(&labeler, &options);
+        counts.push(tier.count_graphs().0);
+    }
+    assert_eq!(counts, vec![2, 2, 3, 1, 0]);
+}
+
+// cancels its token once `after` parent chunks are done
+struct CancelAfter {
+    after: usize,
+    done: AtomicUsize,
+    token: CancelToken,
+}
+
+impl GenerationObserver for CancelAfter {
+    fn observe(&self, event: Event) {
+        if let Event::ChunkProcessed { .. } = event {
+            if self.done.fetch_add(1, Ordering::Relaxed) + 1 >= self.after {
+                self.token.cancel();
+            }
+        }
+    }
+}
+
+#[test]
+fn cancel_and_resume() {
+    let labeler = Pipeline::standard();
+    let options = GenerateOptions::default();
+    let tier = common::tier_up_to(5, &labeler);
+
+    // a spent budget does nothing
+    let past = Budget {
+        cancel: None,
+        deadline: Some(Instant::now()),
+    };
+    let stopped = tier.generate_next_size_budgeted(&labeler, &options, &Silent, &past);
+    assert!(stopped.cancelled);
+    assert!(stopped.completed.is_empty());
+    assert_eq!(stopped.tier.count_graphs().0, 0);
+    assert_eq!(stopped.remaining(&tier).count_graphs().0, 24);
+
+    let observer = CancelAfter {
+        after: 5,
+        done: AtomicUsize::new(0),
+        token: CancelToken::new(),
+    };
+    let budget = Budget {
+        cancel: Some(observer.token.clone()),
+        deadline: None,
+    };
+    let partial = tier.generate_next_size_budgeted(&labeler, &options, &observer, &budget);
+    assert!(partial.cancelled);
+    let remaining = partial.remaining(&tier);
+    assert!(partial.completed.len() >= 5);
+    assert_eq!(partial.completed.len() + remaining.count_chunks(), tier.count_chunks());
+    assert!(partial.tier.count_graphs().0 < 84);
+
+    let rest = remaining.generate_next_size_budgeted(&labeler, &options, &Silent, &Budget::default());
+    assert!(!rest.cancelled);
+    assert_eq!(rest.completed.len(), remaining.count_chunks());
+    let whole = rest.tier.merge(&partial.tier, &labeler);
+    assert_eq!(whole.count_graphs().0, 84);
+}
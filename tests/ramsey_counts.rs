@@ -0,0 +1,78 @@
+// End to end generation against the known numbers of (s, t) Ramsey colorings
+// on n vertices, counted up to isomorphism, from n = 1 until they run out
+
+extern crate graph_lib;
+
+use graph_lib::graph::Graph;
+use graph_lib::labeler::{Labeler, Pipeline};
+use graph_lib::observer::Silent;
+use graph_lib::structures::{GenerateOptions, Tier};
+
+const R33: &[usize] = &[1, 2, 2, 3, 1, 0];
+const R34: &[usize] = &[1, 2, 3, 6, 9, 15, 9, 3, 0];
+const R35: &[usize] = &[1, 2, 3, 7, 13, 32, 71, 179, 290, 313, 105, 12, 1, 0];
+// the (4, 4) colorings go on to 17 vertices, n = 9 already takes a while
+const R44: &[usize] = &[1, 2, 4, 9, 24, 84, 362, 2079];
+
+// the size of every tier from a single vertex until counts runs out
+fn counts<L: Labeler<u32> + ?Sized>(s: u32, t: u32, threads: usize, labeler: &L, len: usize) -> Vec<usize> {
+    let options = GenerateOptions { s, t, threads };
+    let mut tier = Tier::from_graph(Graph::new(1), labeler);
+    let mut out = vec![tier.count_graphs().0];
+    while out.len() < len {
+        tier = tier
+            .generate_next_size_report(labeler, &options, &Silent)
+            .tier;
+        out.push(tier.count_graphs().0);
+    }
+    out
+}
+
+// labelers from strong to weak, the weak ones leave big chunks for the
+// isomorphism check
+fn labelers() -> Vec<(&'static str, Pipeline)> {
+    ["degree,polygons,conv", "wl1", "degree,k3", "degree"]
+        .iter()
+        .map(|spec| (*spec, spec.parse().unwrap()))
+        .collect()
+}
+
+// every labeler with a few workers, and the first with a single one
+fn check(s: u32, t: u32, expected: &[usize]) {
+    let runs = labelers().into_iter().map(|(spec, l)| (spec, l, 3));
+    let (spec, first) = labelers().remove(0);
+    for (spec, labeler, threads) in runs.chain(Some((spec, first, 1))) {
+        assert_eq!(
+            counts(s, t, threads, &labeler, expected.len()),
+            expected,
+            "({}, {}) with {} on {} threads",
+            s,
+            t,
+            spec,
+            threads
+        );
+    }
+}
+
+#[test]
+fn r33() {
+    check(3, 3, R33);
+}
+
+#[test]
+fn r34() {
+    check(3, 4, R34);
+    // swapping the colors swaps s and t
+    assert_eq!(counts(4, 3, 2, &Pipeline::standard(), R34.len()), R34);
+}
+
+#[test]
+fn r35() {
+    check(3, 5, R35);
+    assert_eq!(counts(5, 3, 2, &Pipeline::standard(), R35.len()), R35);
+}
+
+#[test]
+fn r44() {
+    check(4, 4, R44);
+}